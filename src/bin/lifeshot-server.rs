@@ -0,0 +1,32 @@
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    geng::prelude::logger::init().unwrap();
+    let mut args = std::env::args().skip(1);
+    let addr = args
+        .next()
+        .unwrap_or_else(|| lifeshot::Server::DEFAULT_ADDR.to_owned());
+    let config_path = args.next().unwrap_or_else(|| {
+        if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
+            std::env::set_current_dir(std::path::Path::new(&dir).join("static")).unwrap();
+        }
+        "config.json".to_owned()
+    });
+    let waves_path = args.next().unwrap_or_else(|| "waves.json".to_owned());
+    let map_path = args.next().unwrap_or_else(|| "map.json".to_owned());
+    let config = match lifeshot::Config::load(&config_path) {
+        Ok(config) => config,
+        Err(e) => panic!("{:#}", e),
+    };
+    let waves = match lifeshot::WaveScript::load(&waves_path) {
+        Ok(waves) => waves,
+        Err(e) => panic!("{:#}", e),
+    };
+    let map = match lifeshot::Map::load(&map_path) {
+        Ok(map) => map,
+        Err(e) => panic!("{:#}", e),
+    };
+    lifeshot::Server::run(&addr, config, waves, map);
+}
+
+#[cfg(target_arch = "wasm32")]
+fn main() {}
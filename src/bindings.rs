@@ -0,0 +1,365 @@
+use crate::*;
+
+/// Something done with a single key or button, whatever it is bound to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Control {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    Shoot,
+    Restart,
+    Fullscreen,
+    Pause,
+    MassOverlay,
+    SaveReplay,
+    Menu,
+}
+
+/// When a control does anything. Two controls can share a key
+/// if they never do at the same time.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ControlPhase {
+    /// While playing.
+    Alive,
+    /// Once every local player is dead.
+    Dead,
+    Always,
+}
+
+impl ControlPhase {
+    fn overlaps(self, other: Self) -> bool {
+        self == ControlPhase::Always || other == ControlPhase::Always || self == other
+    }
+}
+
+impl Control {
+    pub const ALL: [Self; 11] = [
+        Control::MoveUp,
+        Control::MoveDown,
+        Control::MoveLeft,
+        Control::MoveRight,
+        Control::Shoot,
+        Control::Restart,
+        Control::Fullscreen,
+        Control::Pause,
+        Control::MassOverlay,
+        Control::SaveReplay,
+        Control::Menu,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Control::MoveUp => "move up",
+            Control::MoveDown => "move down",
+            Control::MoveLeft => "move left",
+            Control::MoveRight => "move right",
+            Control::Shoot => "shoot",
+            Control::Restart => "restart",
+            Control::Fullscreen => "fullscreen",
+            Control::Pause => "pause",
+            Control::MassOverlay => "mass ledger",
+            Control::SaveReplay => "save replay",
+            Control::Menu => "back to menu",
+        }
+    }
+
+    pub fn phase(self) -> ControlPhase {
+        match self {
+            Control::MoveUp
+            | Control::MoveDown
+            | Control::MoveLeft
+            | Control::MoveRight
+            | Control::Shoot => ControlPhase::Alive,
+            Control::SaveReplay | Control::Menu => ControlPhase::Dead,
+            Control::Restart | Control::Fullscreen | Control::Pause | Control::MassOverlay => {
+                ControlPhase::Always
+            }
+        }
+    }
+
+    /// Whether the control also works in the menus, where some keys are taken.
+    fn in_menus(self) -> bool {
+        match self {
+            Control::Fullscreen | Control::Pause => true,
+            _ => false,
+        }
+    }
+}
+
+/// A key or a mouse button, written by name in `bindings.json`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Input {
+    Key(geng::Key),
+    Mouse(geng::MouseButton),
+}
+
+impl Input {
+    const KEYS: [geng::Key; 64] = {
+        use geng::Key::*;
+        [
+            Num0, Num1, Num2, Num3, Num4, Num5, Num6, Num7, Num8, Num9, A, B, C, D, E, F, G, H, I,
+            J, K, L, M, N, O, P, Q, R, S, T, U, V, W, X, Y, Z, Escape, Space, Enter, Backspace,
+            LShift, RShift, LCtrl, RCtrl, LAlt, RAlt, Left, Right, Up, Down, PageUp, PageDown, F1,
+            F2, F3, F4, F5, F6, F7, F8, F9, F10, F11, F12,
+        ]
+    };
+    const BUTTONS: [geng::MouseButton; 3] = [
+        geng::MouseButton::Left,
+        geng::MouseButton::Middle,
+        geng::MouseButton::Right,
+    ];
+
+    pub fn name(self) -> String {
+        match self {
+            Input::Key(key) => format!("{:?}", key),
+            Input::Mouse(button) => format!("Mouse{:?}", button),
+        }
+    }
+
+    pub fn is_pressed(self, window: &geng::Window) -> bool {
+        match self {
+            Input::Key(key) => window.is_key_pressed(key),
+            Input::Mouse(button) => window.is_button_pressed(button),
+        }
+    }
+
+    /// The key or button that `event` presses, if it presses one.
+    pub fn pressed_by(event: &geng::Event) -> Option<Self> {
+        match *event {
+            geng::Event::KeyDown { key } => Some(Input::Key(key)),
+            geng::Event::MouseDown { button, .. } => Some(Input::Mouse(button)),
+            _ => None,
+        }
+    }
+}
+
+impl std::convert::TryFrom<String> for Input {
+    type Error = String;
+    fn try_from(name: String) -> Result<Self, String> {
+        Self::KEYS
+            .iter()
+            .map(|&key| Input::Key(key))
+            .chain(Self::BUTTONS.iter().map(|&button| Input::Mouse(button)))
+            .find(|input| input.name() == name)
+            .ok_or_else(|| format!("Unknown key or button {:?}", name))
+    }
+}
+
+impl From<Input> for String {
+    fn from(input: Input) -> Self {
+        input.name()
+    }
+}
+
+/// What the keyboard and mouse controls are bound to, loaded from `bindings.json`.
+///
+/// Every value is optional in the file, missing ones keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Bindings {
+    pub move_up: Input,
+    pub move_down: Input,
+    pub move_left: Input,
+    pub move_right: Input,
+    pub shoot: Input,
+    pub restart: Input,
+    pub fullscreen: Input,
+    pub pause: Input,
+    pub mass_overlay: Input,
+    pub save_replay: Input,
+    pub menu: Input,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        Self {
+            move_up: Input::Key(geng::Key::W),
+            move_down: Input::Key(geng::Key::S),
+            move_left: Input::Key(geng::Key::A),
+            move_right: Input::Key(geng::Key::D),
+            shoot: Input::Mouse(geng::MouseButton::Left),
+            restart: Input::Key(geng::Key::R),
+            fullscreen: Input::Key(geng::Key::F),
+            pause: Input::Key(geng::Key::Escape),
+            mass_overlay: Input::Key(geng::Key::M),
+            // Only used once dead, when moving does nothing
+            save_replay: Input::Key(geng::Key::S),
+            menu: Input::Key(geng::Key::D),
+        }
+    }
+}
+
+impl Bindings {
+    /// Keys switching weapons, which can not be bound to anything else.
+    pub const WEAPON_KEYS: [geng::Key; 5] = [
+        geng::Key::Num1,
+        geng::Key::Num2,
+        geng::Key::Num3,
+        geng::Key::Num4,
+        geng::Key::Num5,
+    ];
+    /// Keys the menus are driven with, see `Control::in_menus`.
+    const MENU_KEYS: [geng::Key; 19] = {
+        use geng::Key::*;
+        [
+            W, A, S, D, Q, E, B, Up, Down, Left, Right, Enter, Space, Backspace, Escape, Num1,
+            Num2, Num3, Num4,
+        ]
+    };
+
+    pub fn get(&self, control: Control) -> Input {
+        match control {
+            Control::MoveUp => self.move_up,
+            Control::MoveDown => self.move_down,
+            Control::MoveLeft => self.move_left,
+            Control::MoveRight => self.move_right,
+            Control::Shoot => self.shoot,
+            Control::Restart => self.restart,
+            Control::Fullscreen => self.fullscreen,
+            Control::Pause => self.pause,
+            Control::MassOverlay => self.mass_overlay,
+            Control::SaveReplay => self.save_replay,
+            Control::Menu => self.menu,
+        }
+    }
+
+    fn get_mut(&mut self, control: Control) -> &mut Input {
+        match control {
+            Control::MoveUp => &mut self.move_up,
+            Control::MoveDown => &mut self.move_down,
+            Control::MoveLeft => &mut self.move_left,
+            Control::MoveRight => &mut self.move_right,
+            Control::Shoot => &mut self.shoot,
+            Control::Restart => &mut self.restart,
+            Control::Fullscreen => &mut self.fullscreen,
+            Control::Pause => &mut self.pause,
+            Control::MassOverlay => &mut self.mass_overlay,
+            Control::SaveReplay => &mut self.save_replay,
+            Control::Menu => &mut self.menu,
+        }
+    }
+
+    /// Every control `input` is bound to.
+    pub fn controls(&self, input: Input) -> impl Iterator<Item = Control> + '_ {
+        Control::ALL
+            .iter()
+            .copied()
+            .filter(move |&control| self.get(control) == input)
+    }
+
+    /// The four movement inputs in one word, like "WASD".
+    pub fn movement_name(&self) -> String {
+        let names = [
+            self.move_up,
+            self.move_left,
+            self.move_down,
+            self.move_right,
+        ]
+        .iter()
+        .map(|input| input.name())
+        .collect::<Vec<_>>();
+        if names.iter().all(|name| name.len() == 1) {
+            names.concat()
+        } else {
+            names.join("/")
+        }
+    }
+
+    pub fn is_pressed(&self, control: Control, window: &geng::Window) -> bool {
+        self.get(control).is_pressed(window)
+    }
+
+    /// Binds `input` to `control`, unless it is one of the `reserved` keys.
+    /// Controls that had it and may be used at the same time get the old input
+    /// of `control` instead, and are returned.
+    pub fn bind(
+        &mut self,
+        control: Control,
+        input: Input,
+        reserved: &[geng::Key],
+    ) -> anyhow::Result<Vec<Control>> {
+        Self::check_free(control, input)?;
+        if let Input::Key(key) = input {
+            if reserved.contains(&key) {
+                anyhow::bail!("{} is used by the arrow keys player", input.name());
+            }
+        }
+        let old = self.get(control);
+        let others: Vec<_> = self
+            .controls(input)
+            .filter(|&other| other != control && other.phase().overlaps(control.phase()))
+            .collect();
+        for &other in &others {
+            Self::check_free(other, old).with_context(|| {
+                format!("{} would have to move to {}", other.name(), old.name())
+            })?;
+        }
+        for &other in &others {
+            *self.get_mut(other) = old;
+        }
+        *self.get_mut(control) = input;
+        Ok(others)
+    }
+
+    fn check_free(control: Control, input: Input) -> anyhow::Result<()> {
+        if let Input::Key(key) = input {
+            // Anything else could not be read back from bindings.json
+            if !Input::KEYS.contains(&key) {
+                anyhow::bail!("{} can not be bound", input.name());
+            }
+            if Self::WEAPON_KEYS.contains(&key) {
+                anyhow::bail!("{} switches weapons", input.name());
+            }
+            // Escape backs out of every menu, which is what pausing is for anyway
+            let escape_to_pause = control == Control::Pause && key == geng::Key::Escape;
+            if control.in_menus() && Self::MENU_KEYS.contains(&key) && !escape_to_pause {
+                anyhow::bail!("{} is used in the menus", input.name());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let bindings: Self = serde_json::from_str(json)?;
+        bindings.validate()?;
+        Ok(bindings)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Invalid bindings in {:?}", path))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    /// Checks that no two controls used at the same time share a key or button.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, &control) in Control::ALL.iter().enumerate() {
+            let input = self.get(control);
+            Self::check_free(control, input)
+                .with_context(|| format!("{} can not be bound to it", control.name()))?;
+            if let Some(&other) = Control::ALL[i + 1..]
+                .iter()
+                .find(|&&other| self.get(other) == input && other.phase().overlaps(control.phase()))
+            {
+                anyhow::bail!(
+                    "{} and {} are both bound to {}",
+                    control.name(),
+                    other.name(),
+                    input.name(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
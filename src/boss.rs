@@ -0,0 +1,51 @@
+use crate::*;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BossAttack {
+    /// Projectiles shot all around at once.
+    Burst,
+    /// Minions spawned next to the boss.
+    Minions,
+    /// Nearby food pulled towards the boss for a while.
+    Vacuum,
+}
+
+/// What makes a player a boss: special attacks used in turns,
+/// carried out by the world.
+pub struct Boss {
+    next_attack: usize,
+    cooldown: f32,
+    vacuum_time_left: f32,
+}
+
+impl Boss {
+    const ATTACKS: [BossAttack; 3] = [BossAttack::Burst, BossAttack::Minions, BossAttack::Vacuum];
+
+    pub fn new(config: &BossConfig) -> Self {
+        Self {
+            next_attack: 0,
+            cooldown: config.attack_interval,
+            vacuum_time_left: 0.0,
+        }
+    }
+
+    /// Advances the timers, returning the attack to start now, if any.
+    pub fn update(&mut self, delta_time: f32, config: &BossConfig) -> Option<BossAttack> {
+        self.vacuum_time_left = (self.vacuum_time_left - delta_time).max(0.0);
+        self.cooldown -= delta_time;
+        if self.cooldown > 0.0 {
+            return None;
+        }
+        self.cooldown = config.attack_interval;
+        let attack = Self::ATTACKS[self.next_attack];
+        self.next_attack = (self.next_attack + 1) % Self::ATTACKS.len();
+        if attack == BossAttack::Vacuum {
+            self.vacuum_time_left = config.vacuum_duration;
+        }
+        Some(attack)
+    }
+
+    pub fn vacuuming(&self) -> bool {
+        self.vacuum_time_left > 0.0
+    }
+}
@@ -0,0 +1,945 @@
+use crate::*;
+
+/// Game balance, loaded from `config.json`.
+///
+/// Every value is optional in the file, missing ones keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub ticks_per_second: f32,
+    /// How far the player has to move from the center for the game to start.
+    pub start_distance: f32,
+    pub arena: ArenaConfig,
+    pub waves: WavesConfig,
+    pub food: FoodConfig,
+    pub status_effects: StatusEffectsConfig,
+    pub player: PlayerConfig,
+    pub projectile: ProjectileConfig,
+    pub weapons: WeaponsConfig,
+    pub bot: BotConfig,
+    pub archetypes: ArchetypesConfig,
+    pub boss: BossConfig,
+    pub gamepad: GamepadConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArenaConfig {
+    /// Half the side of the square world.
+    pub size: f32,
+    pub topology: WorldTopology,
+    /// What the walls of a `BoundedArena` do to projectiles.
+    pub wall_projectiles: ProjectileResponse,
+}
+
+/// What happens at the edges of the world.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WorldTopology {
+    /// Leaving on one side brings you back on the other.
+    Torus,
+    /// Walls all around, which players slide along.
+    BoundedArena,
+}
+
+impl WorldTopology {
+    pub const ALL: [Self; 2] = [WorldTopology::Torus, WorldTopology::BoundedArena];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            WorldTopology::Torus => "Wrapped",
+            WorldTopology::BoundedArena => "Walled",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WavesConfig {
+    pub time_between: f32,
+    /// Time before the next wave once all enemies are dead.
+    pub pause: f32,
+    /// Minimal distance from players at which enemies spawn.
+    pub spawn_distance: f32,
+    /// Bots get one difficulty level harder every this many waves, 0 to never.
+    pub harder_every: usize,
+    /// Distance from a player, along each axis, just out of view, see `SpawnPattern::Edge`.
+    pub edge_distance: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FoodConfig {
+    pub max_count: usize,
+    /// Mass gained per unit of food mass eaten.
+    pub k: f32,
+    pub size: Range<f32>,
+    pub spawn_interval: Range<f32>,
+    /// Share of spawned food that is a power-up, see `FoodKind::POWER_UPS`.
+    pub power_up_chance: f32,
+    pub poison_chance: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusEffectsConfig {
+    pub speed: StatusEffectConfig,
+    pub rapid_charge: StatusEffectConfig,
+    pub cheap_shots: StatusEffectConfig,
+    /// Absorbs the next hit whatever its modifiers, see `World::step`.
+    pub shield: StatusEffectConfig,
+    pub poison: StatusEffectConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatusEffectConfig {
+    /// Seconds the effect lasts once applied.
+    pub duration: f32,
+    pub stacking: Stacking,
+    /// Only used with `Stacking::Stack`.
+    pub max_stacks: usize,
+    pub modifiers: StatModifiers,
+}
+
+/// What happens when an effect is applied to a player already under it.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Stacking {
+    /// The duration starts over.
+    Refresh,
+    /// The duration is added to the time left.
+    Extend,
+    /// Another stack is added, up to `max_stacks`, and the duration starts over.
+    Stack,
+}
+
+/// Changes to a player's stats, multipliers except for `drain`.
+///
+/// Stacks and effects multiply together, drains add up.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StatModifiers {
+    /// Max speed, both moving and aiming.
+    pub speed: f32,
+    pub acceleration: f32,
+    pub projectile_speed: f32,
+    /// How fast shots charge.
+    pub charge_gain: f32,
+    /// How much charging shots costs.
+    pub charge_cost: f32,
+    /// Size lost over time, see `player.death_speed`.
+    pub decay: f32,
+    /// Mass taken by hits.
+    pub damage: f32,
+    /// Mass lost per second.
+    pub drain: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlayerConfig {
+    pub initial_size: f32,
+    pub max_speed: f32,
+    pub max_aiming_speed: f32,
+    pub acceleration: f32,
+    /// Size lost per second once the game has started.
+    pub death_speed: f32,
+    pub projectile_speed: f32,
+    pub projectile_mass_gain_speed: f32,
+    pub projectile_cost_speed: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProjectileConfig {
+    /// Size lost per second of flight.
+    pub death_speed: f32,
+    /// Mass taken from a player per unit of projectile mass.
+    pub strength: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WeaponsConfig {
+    /// Pellets the shotgun splits a shot into.
+    pub shotgun_pellets: usize,
+    /// Angle between the outermost pellets, in degrees.
+    pub shotgun_spread: f32,
+    /// Multiplies the projectile speed.
+    pub lance_speed: f32,
+    /// Share of its mass the lance spends on every player it goes through.
+    pub lance_pierce_cost: f32,
+    /// Multiplies the projectile speed.
+    pub homing_speed: f32,
+    /// Radians per second the orb turns by towards its target.
+    pub homing_turn_speed: f32,
+    /// Enemies further than this are not followed.
+    pub homing_range: f32,
+    /// Multiplies how fast a mine loses size, see `projectile.death_speed`.
+    pub mine_decay: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotConfig {
+    /// Projectile size at which a bot releases the shot.
+    pub shot_hit_size: f32,
+    /// Bots smaller than this, relative to their archetype's size, do not start charging a shot.
+    pub min_size: f32,
+    /// Enemies closer than this are run from, see `BotSkill::retreat_size`.
+    pub retreat_distance: f32,
+    /// Enemies further than this are not seen.
+    pub sight_range: f32,
+    pub easy: BotSkill,
+    pub normal: BotSkill,
+    pub hard: BotSkill,
+    pub nightmare: BotSkill,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BotSkill {
+    /// Seconds it takes a bot to notice where enemies moved.
+    pub reaction_delay: f32,
+    /// Largest angle in radians by which a shot misses its aim.
+    pub aim_error: f32,
+    /// How much of the target's movement is accounted for when aiming, from 0 to 1.
+    pub lead_accuracy: f32,
+    /// Bots smaller than this run from close enemies instead of looking for food.
+    pub retreat_size: f32,
+    /// How strongly incoming projectiles are avoided, from 0 to 1.
+    pub dodging: f32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchetypesConfig {
+    pub grunt: ArchetypeConfig,
+    pub sniper: ArchetypeConfig,
+    pub swarmer: ArchetypeConfig,
+    pub tank: ArchetypeConfig,
+    pub healer: ArchetypeConfig,
+    pub boss: ArchetypeConfig,
+}
+
+/// Stats of one kind of enemy, mostly as multipliers of the `player` ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ArchetypeConfig {
+    /// Initial size.
+    pub size: f32,
+    /// Max speed, both moving and aiming, and acceleration.
+    pub speed: f32,
+    /// How fast shots charge, and how much charging costs.
+    pub charge: f32,
+    /// Mass gained from food.
+    pub food_gain: f32,
+    /// Projectile size at which the bot releases the shot, see `bot.shot_hit_size`.
+    pub shot_size: f32,
+    /// Mass per second given to a touching ally smaller than itself.
+    pub feed_speed: f32,
+    /// How often it shows up in waves compared to the others, 0 for never.
+    pub frequency: f32,
+    /// First wave it may show up in.
+    pub first_wave: usize,
+    pub weapon: Weapon,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BossConfig {
+    /// Seconds between special attacks, which come in turns.
+    pub attack_interval: f32,
+    /// Projectiles shot all around in a burst, paid for with the boss's mass.
+    pub burst_count: usize,
+    pub burst_size: f32,
+    pub minion: Archetype,
+    pub minion_count: usize,
+    /// Food closer than this is pulled in while vacuuming.
+    pub vacuum_radius: f32,
+    pub vacuum_speed: f32,
+    pub vacuum_duration: f32,
+}
+
+/// How `GamepadController` reads the sticks and triggers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    /// Stick deflection ignored around the center, as a fraction of the full range.
+    pub deadzone: f32,
+    /// How far the right trigger has to be pulled to charge a shot.
+    pub trigger_threshold: f32,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            ticks_per_second: 60.0,
+            start_distance: 5.0,
+            arena: default(),
+            waves: default(),
+            food: default(),
+            status_effects: default(),
+            player: default(),
+            projectile: default(),
+            weapons: default(),
+            bot: default(),
+            archetypes: default(),
+            boss: default(),
+            gamepad: default(),
+        }
+    }
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        Self {
+            size: 50.0,
+            topology: WorldTopology::Torus,
+            wall_projectiles: ProjectileResponse::Bounce,
+        }
+    }
+}
+
+impl Default for WavesConfig {
+    fn default() -> Self {
+        Self {
+            time_between: 120.0,
+            pause: 10.0,
+            spawn_distance: 30.0,
+            harder_every: 5,
+            edge_distance: 28.0,
+        }
+    }
+}
+
+impl Default for FoodConfig {
+    fn default() -> Self {
+        Self {
+            max_count: 100,
+            k: 3.0,
+            size: 0.1..0.5,
+            spawn_interval: 0.05..0.1,
+            power_up_chance: 0.03,
+            poison_chance: 0.02,
+        }
+    }
+}
+
+impl Default for StatusEffectsConfig {
+    fn default() -> Self {
+        Self {
+            speed: StatusEffectConfig {
+                modifiers: StatModifiers {
+                    speed: 1.5,
+                    acceleration: 1.5,
+                    ..default()
+                },
+                ..default()
+            },
+            rapid_charge: StatusEffectConfig {
+                modifiers: StatModifiers {
+                    charge_gain: 2.0,
+                    ..default()
+                },
+                ..default()
+            },
+            cheap_shots: StatusEffectConfig {
+                modifiers: StatModifiers {
+                    charge_cost: 0.5,
+                    ..default()
+                },
+                ..default()
+            },
+            shield: StatusEffectConfig {
+                stacking: Stacking::Extend,
+                ..default()
+            },
+            poison: StatusEffectConfig {
+                stacking: Stacking::Stack,
+                max_stacks: 3,
+                modifiers: StatModifiers {
+                    drain: 0.1,
+                    ..default()
+                },
+                ..default()
+            },
+        }
+    }
+}
+
+impl Default for StatusEffectConfig {
+    fn default() -> Self {
+        Self {
+            duration: 10.0,
+            stacking: Stacking::Refresh,
+            max_stacks: 1,
+            modifiers: default(),
+        }
+    }
+}
+
+impl Default for StatModifiers {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            acceleration: 1.0,
+            projectile_speed: 1.0,
+            charge_gain: 1.0,
+            charge_cost: 1.0,
+            decay: 1.0,
+            damage: 1.0,
+            drain: 0.0,
+        }
+    }
+}
+
+impl Default for WeaponsConfig {
+    fn default() -> Self {
+        Self {
+            shotgun_pellets: 5,
+            shotgun_spread: 40.0,
+            lance_speed: 1.5,
+            lance_pierce_cost: 0.3,
+            homing_speed: 0.6,
+            homing_turn_speed: 3.0,
+            homing_range: 20.0,
+            mine_decay: 0.2,
+        }
+    }
+}
+
+impl Default for PlayerConfig {
+    fn default() -> Self {
+        Self {
+            initial_size: 1.0,
+            max_speed: 8.0,
+            max_aiming_speed: 4.0,
+            acceleration: 15.0,
+            death_speed: 1.0 / 20.0,
+            projectile_speed: 25.0,
+            projectile_mass_gain_speed: 0.3,
+            projectile_cost_speed: 0.1,
+        }
+    }
+}
+
+impl Default for ProjectileConfig {
+    fn default() -> Self {
+        Self {
+            death_speed: 0.1,
+            strength: 0.5,
+        }
+    }
+}
+
+impl Default for BotConfig {
+    fn default() -> Self {
+        Self {
+            shot_hit_size: 0.3,
+            min_size: 0.7,
+            retreat_distance: 10.0,
+            sight_range: 40.0,
+            easy: BotSkill {
+                reaction_delay: 0.6,
+                aim_error: 0.25,
+                lead_accuracy: 0.0,
+                retreat_size: 0.0,
+                dodging: 0.0,
+            },
+            normal: default(),
+            hard: BotSkill {
+                reaction_delay: 0.15,
+                aim_error: 0.04,
+                lead_accuracy: 0.9,
+                retreat_size: 0.6,
+                dodging: 0.7,
+            },
+            nightmare: BotSkill {
+                reaction_delay: 0.0,
+                aim_error: 0.0,
+                lead_accuracy: 1.0,
+                retreat_size: 0.7,
+                dodging: 1.0,
+            },
+        }
+    }
+}
+
+impl Default for ArchetypesConfig {
+    fn default() -> Self {
+        Self {
+            grunt: ArchetypeConfig {
+                frequency: 4.0,
+                ..default()
+            },
+            sniper: ArchetypeConfig {
+                size: 1.2,
+                speed: 0.8,
+                charge: 1.5,
+                shot_size: 3.0,
+                first_wave: 3,
+                weapon: Weapon::Lance,
+                ..default()
+            },
+            swarmer: ArchetypeConfig {
+                size: 0.6,
+                speed: 1.5,
+                shot_size: 0.5,
+                frequency: 2.0,
+                first_wave: 2,
+                ..default()
+            },
+            tank: ArchetypeConfig {
+                size: 1.6,
+                speed: 0.6,
+                charge: 0.8,
+                food_gain: 2.0,
+                shot_size: 1.5,
+                first_wave: 4,
+                weapon: Weapon::Shotgun,
+                ..default()
+            },
+            healer: ArchetypeConfig {
+                feed_speed: 0.2,
+                first_wave: 5,
+                ..default()
+            },
+            boss: ArchetypeConfig {
+                size: 4.0,
+                speed: 0.5,
+                charge: 2.0,
+                shot_size: 4.0,
+                frequency: 0.0,
+                ..default()
+            },
+        }
+    }
+}
+
+impl Default for BossConfig {
+    fn default() -> Self {
+        Self {
+            attack_interval: 5.0,
+            burst_count: 16,
+            burst_size: 0.5,
+            minion: Archetype::Swarmer,
+            minion_count: 3,
+            vacuum_radius: 20.0,
+            vacuum_speed: 6.0,
+            vacuum_duration: 3.0,
+        }
+    }
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.2,
+            trigger_threshold: 0.5,
+        }
+    }
+}
+
+impl Default for ArchetypeConfig {
+    fn default() -> Self {
+        Self {
+            size: 1.0,
+            speed: 1.0,
+            charge: 1.0,
+            food_gain: 1.0,
+            shot_size: 1.0,
+            feed_speed: 0.0,
+            frequency: 1.0,
+            first_wave: 1,
+            weapon: Weapon::Blob,
+        }
+    }
+}
+
+impl Default for BotSkill {
+    fn default() -> Self {
+        Self {
+            reaction_delay: 0.3,
+            aim_error: 0.1,
+            lead_accuracy: 0.5,
+            retreat_size: 0.5,
+            dodging: 0.3,
+        }
+    }
+}
+
+impl BotConfig {
+    pub fn skill(&self, difficulty: Difficulty) -> &BotSkill {
+        match difficulty {
+            Difficulty::Easy => &self.easy,
+            Difficulty::Normal => &self.normal,
+            Difficulty::Hard => &self.hard,
+            Difficulty::Nightmare => &self.nightmare,
+        }
+    }
+}
+
+impl BotSkill {
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        check_non_negative(&format!("{}.reaction_delay", name), self.reaction_delay)?;
+        check_non_negative(&format!("{}.aim_error", name), self.aim_error)?;
+        check_fraction(&format!("{}.lead_accuracy", name), self.lead_accuracy)?;
+        check_non_negative(&format!("{}.retreat_size", name), self.retreat_size)?;
+        check_fraction(&format!("{}.dodging", name), self.dodging)?;
+        Ok(())
+    }
+}
+
+impl ArchetypesConfig {
+    pub fn get(&self, archetype: Archetype) -> &ArchetypeConfig {
+        match archetype {
+            Archetype::Grunt => &self.grunt,
+            Archetype::Sniper => &self.sniper,
+            Archetype::Swarmer => &self.swarmer,
+            Archetype::Tank => &self.tank,
+            Archetype::Healer => &self.healer,
+            Archetype::Boss => &self.boss,
+        }
+    }
+}
+
+impl ArchetypeConfig {
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        check_positive(&format!("{}.size", name), self.size)?;
+        check_non_negative(&format!("{}.speed", name), self.speed)?;
+        check_non_negative(&format!("{}.charge", name), self.charge)?;
+        check_non_negative(&format!("{}.food_gain", name), self.food_gain)?;
+        check_positive(&format!("{}.shot_size", name), self.shot_size)?;
+        check_non_negative(&format!("{}.feed_speed", name), self.feed_speed)?;
+        check_non_negative(&format!("{}.frequency", name), self.frequency)?;
+        Ok(())
+    }
+}
+
+impl StatusEffectsConfig {
+    pub fn get(&self, kind: StatusKind) -> &StatusEffectConfig {
+        match kind {
+            StatusKind::Speed => &self.speed,
+            StatusKind::RapidCharge => &self.rapid_charge,
+            StatusKind::CheapShots => &self.cheap_shots,
+            StatusKind::Shield => &self.shield,
+            StatusKind::Poison => &self.poison,
+        }
+    }
+}
+
+impl StatusEffectConfig {
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        check_positive(&format!("{}.duration", name), self.duration)?;
+        if self.max_stacks == 0 {
+            anyhow::bail!("{}.max_stacks must be positive", name);
+        }
+        self.modifiers.validate(&format!("{}.modifiers", name))
+    }
+}
+
+impl StatModifiers {
+    fn validate(&self, name: &str) -> anyhow::Result<()> {
+        check_non_negative(&format!("{}.speed", name), self.speed)?;
+        check_non_negative(&format!("{}.acceleration", name), self.acceleration)?;
+        check_positive(&format!("{}.projectile_speed", name), self.projectile_speed)?;
+        check_non_negative(&format!("{}.charge_gain", name), self.charge_gain)?;
+        check_non_negative(&format!("{}.charge_cost", name), self.charge_cost)?;
+        check_non_negative(&format!("{}.decay", name), self.decay)?;
+        check_non_negative(&format!("{}.damage", name), self.damage)?;
+        check_non_negative(&format!("{}.drain", name), self.drain)?;
+        Ok(())
+    }
+
+    /// The modifiers of `stacks` stacks of the same effect.
+    pub fn stacked(self, stacks: usize) -> Self {
+        let stacks = stacks as i32;
+        Self {
+            speed: self.speed.powi(stacks),
+            acceleration: self.acceleration.powi(stacks),
+            projectile_speed: self.projectile_speed.powi(stacks),
+            charge_gain: self.charge_gain.powi(stacks),
+            charge_cost: self.charge_cost.powi(stacks),
+            decay: self.decay.powi(stacks),
+            damage: self.damage.powi(stacks),
+            drain: self.drain * stacks as f32,
+        }
+    }
+
+    /// Both modifiers applied together.
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            speed: self.speed * other.speed,
+            acceleration: self.acceleration * other.acceleration,
+            projectile_speed: self.projectile_speed * other.projectile_speed,
+            charge_gain: self.charge_gain * other.charge_gain,
+            charge_cost: self.charge_cost * other.charge_cost,
+            decay: self.decay * other.decay,
+            damage: self.damage * other.damage,
+            drain: self.drain + other.drain,
+        }
+    }
+}
+
+impl ArenaConfig {
+    pub fn wraps(&self) -> bool {
+        self.topology == WorldTopology::Torus
+    }
+
+    /// Shortest way from `a` to `b`, possibly across the edges of a torus.
+    pub fn delta_pos(&self, a: Vec2<f32>, b: Vec2<f32>) -> Vec2<f32> {
+        let dv = b - a;
+        self.normalize(dv)
+    }
+
+    /// Wraps a position or a delta around a torus, leaves it as is otherwise.
+    pub fn normalize(&self, mut v: Vec2<f32>) -> Vec2<f32> {
+        if !self.wraps() {
+            return v;
+        }
+        while v.x > self.size {
+            v.x -= 2.0 * self.size;
+        }
+        while v.y > self.size {
+            v.y -= 2.0 * self.size;
+        }
+        while v.x < -self.size {
+            v.x += 2.0 * self.size;
+        }
+        while v.y < -self.size {
+            v.y += 2.0 * self.size;
+        }
+        v
+    }
+
+    /// A position in the arena for `pos`: wrapped around a torus,
+    /// or moved at least `margin` inside the walls.
+    pub fn confine(&self, pos: Vec2<f32>, margin: f32) -> Vec2<f32> {
+        if self.wraps() {
+            return self.normalize(pos);
+        }
+        let limit = (self.size - margin).max(0.0);
+        pos.map(|x| clamp(x, -limit..=limit))
+    }
+
+    /// How a circle at `pos` pokes through the walls, if there are any.
+    pub fn wall_contact(&self, pos: Vec2<f32>, radius: f32) -> Option<Contact> {
+        if self.wraps() {
+            return None;
+        }
+        let push = self.confine(pos, radius) - pos;
+        let depth = push.len();
+        if depth > 0.0 {
+            Some(Contact {
+                normal: push / depth,
+                depth,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn check_positive(name: &str, value: f32) -> anyhow::Result<()> {
+    if !value.is_finite() || value <= 0.0 {
+        anyhow::bail!("{} must be positive, got {}", name, value);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_non_negative(name: &str, value: f32) -> anyhow::Result<()> {
+    if !value.is_finite() || value < 0.0 {
+        anyhow::bail!("{} must not be negative, got {}", name, value);
+    }
+    Ok(())
+}
+
+pub(crate) fn check_fraction(name: &str, value: f32) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&value) {
+        anyhow::bail!("{} must be between 0 and 1, got {}", name, value);
+    }
+    Ok(())
+}
+
+fn check_range(name: &str, range: &Range<f32>) -> anyhow::Result<()> {
+    check_non_negative(&format!("{}.start", name), range.start)?;
+    check_non_negative(&format!("{}.end", name), range.end)?;
+    if range.start > range.end {
+        anyhow::bail!(
+            "{}.start must not be greater than {}.end, got {} > {}",
+            name,
+            name,
+            range.start,
+            range.end,
+        );
+    }
+    Ok(())
+}
+
+impl Config {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let config: Self = serde_json::from_str(json)?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Invalid config in {:?}", path))
+    }
+
+    pub fn tick_duration(&self) -> f32 {
+        1.0 / self.ticks_per_second
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        check_positive("ticks_per_second", self.ticks_per_second)?;
+        check_non_negative("start_distance", self.start_distance)?;
+
+        check_positive("arena.size", self.arena.size)?;
+
+        check_positive("waves.time_between", self.waves.time_between)?;
+        check_non_negative("waves.pause", self.waves.pause)?;
+        check_non_negative("waves.spawn_distance", self.waves.spawn_distance)?;
+        if self.waves.spawn_distance >= self.arena.size {
+            anyhow::bail!(
+                "waves.spawn_distance must be less than arena.size, got {} >= {}",
+                self.waves.spawn_distance,
+                self.arena.size,
+            );
+        }
+        check_positive("waves.edge_distance", self.waves.edge_distance)?;
+
+        check_positive("food.k", self.food.k)?;
+        check_range("food.size", &self.food.size)?;
+        check_positive("food.size.start", self.food.size.start)?;
+        check_range("food.spawn_interval", &self.food.spawn_interval)?;
+        check_positive("food.spawn_interval.start", self.food.spawn_interval.start)?;
+        check_fraction("food.power_up_chance", self.food.power_up_chance)?;
+        check_fraction("food.poison_chance", self.food.poison_chance)?;
+        if self.food.power_up_chance + self.food.poison_chance > 1.0 {
+            anyhow::bail!(
+                "food.power_up_chance and food.poison_chance must add up to at most 1, got {} + {}",
+                self.food.power_up_chance,
+                self.food.poison_chance,
+            );
+        }
+
+        let effects = &self.status_effects;
+        effects.speed.validate("status_effects.speed")?;
+        effects
+            .rapid_charge
+            .validate("status_effects.rapid_charge")?;
+        effects.cheap_shots.validate("status_effects.cheap_shots")?;
+        effects.shield.validate("status_effects.shield")?;
+        effects.poison.validate("status_effects.poison")?;
+
+        check_positive("player.initial_size", self.player.initial_size)?;
+        check_non_negative("player.max_speed", self.player.max_speed)?;
+        check_non_negative("player.max_aiming_speed", self.player.max_aiming_speed)?;
+        check_non_negative("player.acceleration", self.player.acceleration)?;
+        check_non_negative("player.death_speed", self.player.death_speed)?;
+        check_positive("player.projectile_speed", self.player.projectile_speed)?;
+        check_non_negative(
+            "player.projectile_mass_gain_speed",
+            self.player.projectile_mass_gain_speed,
+        )?;
+        check_non_negative(
+            "player.projectile_cost_speed",
+            self.player.projectile_cost_speed,
+        )?;
+
+        check_non_negative("projectile.death_speed", self.projectile.death_speed)?;
+        check_non_negative("projectile.strength", self.projectile.strength)?;
+
+        if self.weapons.shotgun_pellets == 0 {
+            anyhow::bail!("weapons.shotgun_pellets must be positive");
+        }
+        check_non_negative("weapons.shotgun_spread", self.weapons.shotgun_spread)?;
+        check_positive("weapons.lance_speed", self.weapons.lance_speed)?;
+        check_fraction("weapons.lance_pierce_cost", self.weapons.lance_pierce_cost)?;
+        check_positive("weapons.homing_speed", self.weapons.homing_speed)?;
+        check_non_negative("weapons.homing_turn_speed", self.weapons.homing_turn_speed)?;
+        check_non_negative("weapons.homing_range", self.weapons.homing_range)?;
+        check_non_negative("weapons.mine_decay", self.weapons.mine_decay)?;
+
+        check_non_negative("bot.shot_hit_size", self.bot.shot_hit_size)?;
+        check_non_negative("bot.min_size", self.bot.min_size)?;
+        check_non_negative("bot.retreat_distance", self.bot.retreat_distance)?;
+        check_positive("bot.sight_range", self.bot.sight_range)?;
+        self.bot.easy.validate("bot.easy")?;
+        self.bot.normal.validate("bot.normal")?;
+        self.bot.hard.validate("bot.hard")?;
+        self.bot.nightmare.validate("bot.nightmare")?;
+
+        self.archetypes.grunt.validate("archetypes.grunt")?;
+        self.archetypes.sniper.validate("archetypes.sniper")?;
+        self.archetypes.swarmer.validate("archetypes.swarmer")?;
+        self.archetypes.tank.validate("archetypes.tank")?;
+        self.archetypes.healer.validate("archetypes.healer")?;
+        self.archetypes.boss.validate("archetypes.boss")?;
+
+        check_positive("boss.attack_interval", self.boss.attack_interval)?;
+        check_positive("boss.burst_size", self.boss.burst_size)?;
+        if self.boss.minion == Archetype::Boss {
+            anyhow::bail!("boss.minion can not be Boss");
+        }
+        check_non_negative("boss.vacuum_radius", self.boss.vacuum_radius)?;
+        check_non_negative("boss.vacuum_speed", self.boss.vacuum_speed)?;
+        check_non_negative("boss.vacuum_duration", self.boss.vacuum_duration)?;
+
+        check_fraction("gamepad.deadzone", self.gamepad.deadzone)?;
+        if self.gamepad.deadzone == 1.0 {
+            anyhow::bail!("gamepad.deadzone must be less than 1");
+        }
+        check_fraction("gamepad.trigger_threshold", self.gamepad.trigger_threshold)?;
+        Ok(())
+    }
+}
+
+/// Watches a config file on disk, reloading it whenever it changes.
+#[cfg(not(target_arch = "wasm32"))]
+pub struct ConfigWatcher {
+    path: std::path::PathBuf,
+    modified: Option<std::time::SystemTime>,
+    next_check: f32,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl ConfigWatcher {
+    const CHECK_INTERVAL: f32 = 0.5;
+
+    pub fn new(path: impl Into<std::path::PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            modified: Self::modified(&path),
+            path,
+            next_check: Self::CHECK_INTERVAL,
+        }
+    }
+
+    fn modified(path: &std::path::Path) -> Option<std::time::SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Returns the reloaded config, or why it could not be loaded,
+    /// if the file changed since the last check.
+    pub fn update(&mut self, delta_time: f32) -> Option<anyhow::Result<Config>> {
+        self.next_check -= delta_time;
+        if self.next_check > 0.0 {
+            return None;
+        }
+        self.next_check = Self::CHECK_INTERVAL;
+        let modified = Self::modified(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(Config::load(&self.path))
+    }
+}
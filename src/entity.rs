@@ -1,17 +1,23 @@
 use crate::*;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Entity {
     pub owner_id: Option<usize>,
     pub color: Color<f32>,
     pub pos: Vec2<f32>,
+    pub prev_pos: Vec2<f32>,
     pub vel: Vec2<f32>,
     pub size: f32,
 }
 
 impl Entity {
-    pub fn draw(&self, buffer: &mut Vec<ParticleInstance>) {
+    /// Position between the previous and the current tick, `alpha` going from 0 to 1.
+    pub fn render_pos(&self, alpha: f32) -> Vec2<f32> {
+        self.prev_pos + (self.pos - self.prev_pos) * alpha
+    }
+    pub fn draw(&self, alpha: f32, buffer: &mut Vec<ParticleInstance>) {
         buffer.push(ParticleInstance {
-            i_pos: self.pos,
+            i_pos: self.render_pos(alpha),
             i_size: self.size,
             i_color: self.color,
         });
@@ -23,14 +29,39 @@ impl Entity {
         let mass = self.size * self.size + delta;
         self.size = mass.max(0.0).sqrt();
     }
-    pub fn update(&mut self, delta_time: f32) {
-        self.pos += self.vel * delta_time;
-        self.pos = Game::normalize(self.pos);
+    /// Moves along the velocity, wrapping around a torus.
+    /// Walls are left to the world, which knows what to do at them.
+    pub fn update(&mut self, delta_time: f32, arena: &ArenaConfig) {
+        self.prev_pos = self.pos;
+        let pos = self.pos + self.vel * delta_time;
+        self.pos = arena.normalize(pos);
+        // Wrap the previous position too, so that interpolation does not cross the world
+        self.prev_pos += self.pos - pos;
+    }
+    /// Radius around the current position that contains the entity
+    /// during the whole last tick.
+    pub fn swept_radius(&self) -> f32 {
+        self.size + (self.pos - self.prev_pos).len()
+    }
+    /// Smallest distance between the centers of `a` and `b` while they moved
+    /// from their previous to their current positions, so that fast objects
+    /// can not pass through each other between ticks.
+    pub fn swept_distance(a: &Self, b: &Self, arena: &ArenaConfig) -> f32 {
+        let end = arena.delta_pos(b.pos, a.pos);
+        let motion = (a.pos - a.prev_pos) - (b.pos - b.prev_pos);
+        let start = end - motion;
+        let len = motion.len();
+        let t = if len > 1e-5 {
+            clamp(-Vec2::dot(start, motion) / (len * len), 0.0..=1.0)
+        } else {
+            1.0
+        };
+        (start + motion * t).len()
     }
-    pub fn collide(a: &mut Self, b: &mut Self) {
-        let penetration = (a.size + b.size) - Game::normalize(a.pos - b.pos).len();
+    pub fn collide(a: &mut Self, b: &mut Self, arena: &ArenaConfig) {
+        let penetration = (a.size + b.size) - arena.normalize(a.pos - b.pos).len();
         let penetration = penetration.min(partial_min(a.size, b.size));
-        let n = Game::normalize(b.pos - a.pos).normalize();
+        let n = arena.normalize(b.pos - a.pos).normalize();
         if penetration > 0.0 {
             let ka = 1.0 / a.mass();
             let kb = 1.0 / b.mass();
@@ -41,8 +72,11 @@ impl Entity {
             b.pos += n * penetration * kb;
         }
     }
-    pub fn hit(&mut self, target: &mut Self, k: f32) -> bool {
-        let penetration = (self.size + target.size) - Game::normalize(self.pos - target.pos).len();
+    /// Takes `k` mass from `target` per unit of mass lost by `self`, giving back
+    /// what the target did not have. `k` is the projectile strength times the damage
+    /// modifier of the target's status effects, 0 letting `self` through untouched.
+    pub fn hit(&mut self, target: &mut Self, k: f32, arena: &ArenaConfig) -> bool {
+        let penetration = (self.size + target.size) - Self::swept_distance(self, target, arena);
         let penetration = penetration.min(partial_min(self.size, target.size));
         if penetration > 0.0 {
             let prev_mass = self.mass();
@@ -50,15 +84,19 @@ impl Entity {
             let delta_mass = prev_mass - self.mass();
             let prev_target_mass = target.mass();
             target.add_mass(-delta_mass * k);
-            let real_delta_mass = (prev_target_mass - target.mass()) / k;
+            let real_delta_mass = if k > 0.0 {
+                (prev_target_mass - target.mass()) / k
+            } else {
+                0.0
+            };
             self.add_mass(delta_mass - real_delta_mass);
             true
         } else {
             false
         }
     }
-    pub fn consume(&mut self, target: &mut Self, k: f32) {
-        let penetration = (self.size + target.size) - Game::normalize(self.pos - target.pos).len();
+    pub fn consume(&mut self, target: &mut Self, k: f32, arena: &ArenaConfig) {
+        let penetration = (self.size + target.size) - arena.normalize(self.pos - target.pos).len();
         let penetration = penetration.min(partial_min(self.size, target.size));
         if penetration > 0.0 {
             let prev_mass = target.mass();
@@ -68,3 +106,68 @@ impl Entity {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SPEED: f32 = 25.0;
+    const DELTA_TIME: f32 = 0.5;
+
+    fn entity(pos: Vec2<f32>, vel: Vec2<f32>, size: f32) -> Entity {
+        Entity {
+            owner_id: None,
+            color: Color::WHITE,
+            pos,
+            prev_pos: pos,
+            vel,
+            size,
+        }
+    }
+
+    /// Steps a projectile from `start` to the right for one long tick,
+    /// and hits a still target at `target`.
+    fn shoot(start: Vec2<f32>, target: Vec2<f32>, size: f32, arena: &ArenaConfig) -> bool {
+        let mut projectile = entity(start, vec2(SPEED, 0.0), 0.2);
+        let mut target = entity(target, vec2(0.0, 0.0), size);
+        projectile.update(DELTA_TIME, arena);
+        projectile.hit(&mut target, 1.0, arena)
+    }
+
+    #[test]
+    fn fast_projectile_hits_small_targets() {
+        let arena = ArenaConfig::default();
+        for &size in &[0.1, 0.5] {
+            // The projectile ends the tick 12.5 away, well past the target
+            assert!(shoot(vec2(-10.0, 0.0), vec2(-4.0, 0.0), size, &arena));
+        }
+    }
+
+    #[test]
+    fn fast_projectile_hits_across_the_wrap() {
+        let arena = ArenaConfig::default();
+        let start = vec2(arena.size - 5.0, 0.0);
+        for &size in &[0.1, 0.5] {
+            // Just past the edge, on the other side of the world
+            assert!(shoot(start, vec2(-arena.size + 2.0, 0.0), size, &arena));
+        }
+    }
+
+    #[test]
+    fn fast_projectile_hits_in_walled_arena() {
+        let arena = ArenaConfig {
+            topology: WorldTopology::BoundedArena,
+            ..default()
+        };
+        for &size in &[0.1, 0.5] {
+            assert!(shoot(vec2(-10.0, 0.0), vec2(-4.0, 0.0), size, &arena));
+        }
+    }
+
+    #[test]
+    fn fast_projectile_misses_beside_its_path() {
+        let arena = ArenaConfig::default();
+        // Closest approach 0.5, more than the 0.3 the two sizes add up to
+        assert!(!shoot(vec2(-10.0, 0.0), vec2(-4.0, 0.5), 0.1, &arena));
+    }
+}
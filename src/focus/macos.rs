@@ -0,0 +1,19 @@
+use objc::runtime::{Object, BOOL, NO};
+use objc::{class, msg_send, sel, sel_impl};
+
+/// The application is active, it has only the one window.
+pub struct Platform;
+
+impl Platform {
+    pub fn new() -> Option<Self> {
+        Some(Self)
+    }
+
+    pub fn has_focus(&mut self) -> Option<bool> {
+        let active: BOOL = unsafe {
+            let app: *mut Object = msg_send![class!(NSApplication), sharedApplication];
+            msg_send![app, isActive]
+        };
+        Some(active != NO)
+    }
+}
@@ -0,0 +1,84 @@
+use crate::*;
+
+#[cfg(target_os = "macos")]
+mod macos;
+#[cfg(not(any(
+    target_arch = "wasm32",
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+mod other;
+#[cfg(target_arch = "wasm32")]
+mod web;
+#[cfg(target_os = "windows")]
+mod windows;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+mod x11;
+
+#[cfg(target_os = "macos")]
+use macos::Platform;
+#[cfg(not(any(
+    target_arch = "wasm32",
+    target_os = "windows",
+    target_os = "macos",
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+)))]
+use other::Platform;
+#[cfg(target_arch = "wasm32")]
+use web::Platform;
+#[cfg(target_os = "windows")]
+use windows::Platform;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "freebsd",
+    target_os = "dragonfly",
+    target_os = "netbsd",
+    target_os = "openbsd",
+))]
+use x11::Platform;
+
+/// Whether the game window has the keyboard. geng reports no focus changes,
+/// so this asks the browser or the desktop every time.
+pub struct Focus {
+    /// `None` where focus can not be told.
+    platform: Option<Platform>,
+}
+
+impl Focus {
+    pub fn new() -> Self {
+        let platform = Platform::new();
+        if platform.is_none() {
+            info!("Window focus can not be told here, only a hidden window pauses");
+        }
+        Self { platform }
+    }
+
+    /// `true` where it can not be told.
+    pub fn has_focus(&mut self) -> bool {
+        self.platform
+            .as_mut()
+            .and_then(|platform| platform.has_focus())
+            .unwrap_or(true)
+    }
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
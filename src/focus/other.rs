@@ -0,0 +1,12 @@
+/// Nothing to ask on this platform.
+pub enum Platform {}
+
+impl Platform {
+    pub fn new() -> Option<Self> {
+        None
+    }
+
+    pub fn has_focus(&mut self) -> Option<bool> {
+        match *self {}
+    }
+}
@@ -0,0 +1,15 @@
+use super::*;
+
+pub struct Platform;
+
+impl Platform {
+    pub fn new() -> Option<Self> {
+        Some(Self)
+    }
+
+    pub fn has_focus(&mut self) -> Option<bool> {
+        web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.has_focus().ok())
+    }
+}
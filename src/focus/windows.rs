@@ -0,0 +1,20 @@
+use winapi::um::winuser::{GetForegroundWindow, GetWindowThreadProcessId};
+
+/// The foreground window belongs to this process, the only one it opens.
+pub struct Platform;
+
+impl Platform {
+    pub fn new() -> Option<Self> {
+        Some(Self)
+    }
+
+    pub fn has_focus(&mut self) -> Option<bool> {
+        let window = unsafe { GetForegroundWindow() };
+        if window.is_null() {
+            return Some(false);
+        }
+        let mut pid = 0;
+        unsafe { GetWindowThreadProcessId(window, &mut pid) };
+        Some(pid == std::process::id())
+    }
+}
@@ -0,0 +1,94 @@
+use std::os::raw::{c_int, c_uchar, c_ulong, c_void};
+use x11_dl::xlib;
+
+/// The window the window manager calls active belongs to this process,
+/// going by `_NET_ACTIVE_WINDOW` and `_NET_WM_PID`.
+pub struct Platform {
+    xlib: xlib::Xlib,
+    display: *mut xlib::Display,
+    active_window: xlib::Atom,
+    pid: xlib::Atom,
+}
+
+impl Platform {
+    pub fn new() -> Option<Self> {
+        // Under Wayland the game window is not an X window, so X can not tell
+        let backend = std::env::var("WINIT_UNIX_BACKEND").ok();
+        if std::env::var_os("WAYLAND_DISPLAY").is_some() && backend.as_deref() != Some("x11") {
+            return None;
+        }
+        let xlib = xlib::Xlib::open().ok()?;
+        let display = unsafe { (xlib.XOpenDisplay)(std::ptr::null()) };
+        if display.is_null() {
+            return None;
+        }
+        let atom = |name: &[u8]| unsafe {
+            (xlib.XInternAtom)(display, name.as_ptr() as *const _, xlib::False)
+        };
+        let active_window = atom(b"_NET_ACTIVE_WINDOW\0");
+        let pid = atom(b"_NET_WM_PID\0");
+        Some(Self {
+            xlib,
+            display,
+            active_window,
+            pid,
+        })
+    }
+
+    /// `None` if the window manager does not say.
+    pub fn has_focus(&mut self) -> Option<bool> {
+        let root = unsafe { (self.xlib.XDefaultRootWindow)(self.display) };
+        let window = self.property(root, self.active_window, xlib::XA_WINDOW)?;
+        if window == 0 {
+            return Some(false);
+        }
+        let pid = self.property(window, self.pid, xlib::XA_CARDINAL)?;
+        Some(pid == std::process::id() as c_ulong)
+    }
+
+    /// The first item of a 32-bit window property, which Xlib hands out as a long.
+    fn property(
+        &self,
+        window: xlib::Window,
+        property: xlib::Atom,
+        kind: xlib::Atom,
+    ) -> Option<c_ulong> {
+        let mut actual_kind = 0;
+        let mut format = 0;
+        let mut items = 0;
+        let mut bytes_after = 0;
+        let mut data: *mut c_uchar = std::ptr::null_mut();
+        let status = unsafe {
+            (self.xlib.XGetWindowProperty)(
+                self.display,
+                window,
+                property,
+                0,
+                1,
+                xlib::False,
+                kind,
+                &mut actual_kind,
+                &mut format,
+                &mut items,
+                &mut bytes_after,
+                &mut data,
+            )
+        };
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+        let value = if actual_kind == kind && format == 32 && items > 0 {
+            Some(unsafe { *(data as *const c_ulong) })
+        } else {
+            None
+        };
+        unsafe { (self.xlib.XFree)(data as *mut c_void) };
+        value
+    }
+}
+
+impl Drop for Platform {
+    fn drop(&mut self) {
+        unsafe { (self.xlib.XCloseDisplay)(self.display) };
+    }
+}
@@ -1,7 +1,49 @@
 use crate::*;
 
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FoodKind {
+    Plain,
+    /// Faster movement.
+    Speed,
+    /// Shots charge faster.
+    RapidCharge,
+    /// Charging shots costs less.
+    CheapShots,
+    /// The next hit is absorbed.
+    Shield,
+    /// Gives no mass, and drains it for a while.
+    Poison,
+}
+
+impl FoodKind {
+    /// Kinds that do their eater good, all but `Plain` and `Poison`.
+    pub const POWER_UPS: [Self; 4] = [
+        FoodKind::Speed,
+        FoodKind::RapidCharge,
+        FoodKind::CheapShots,
+        FoodKind::Shield,
+    ];
+
+    /// Status effect given to whoever finishes the food.
+    pub fn effect(self) -> Option<StatusKind> {
+        match self {
+            FoodKind::Plain => None,
+            FoodKind::Speed => Some(StatusKind::Speed),
+            FoodKind::RapidCharge => Some(StatusKind::RapidCharge),
+            FoodKind::CheapShots => Some(StatusKind::CheapShots),
+            FoodKind::Shield => Some(StatusKind::Shield),
+            FoodKind::Poison => Some(StatusKind::Poison),
+        }
+    }
+
+    pub fn color(self) -> Color<f32> {
+        self.effect().map_or(Color::GREEN, StatusKind::color)
+    }
+}
+
 pub struct Food {
     entity: Entity,
+    pub kind: FoodKind,
     parts: Vec<(Vec2<f32>, Entity)>,
     time: f32,
 }
@@ -22,28 +64,32 @@ impl DerefMut for Food {
 impl Food {
     const PREFERRED_MASS: f32 = 0.02;
     const COLOR_OFF: f32 = 0.3;
-    pub fn new(pos: Vec2<f32>, size: f32) -> Self {
+    pub fn new<R: Rng>(pos: Vec2<f32>, size: f32, kind: FoodKind, rng: &mut R) -> Self {
         let part_count = f32::ceil(size * size / Self::PREFERRED_MASS) as usize;
+        let color = kind.color();
         Self {
+            kind,
             entity: Entity {
                 owner_id: None,
-                color: Color::GREEN,
+                color,
                 pos,
+                prev_pos: pos,
                 vel: vec2(0.0, 0.0),
                 size,
             },
             parts: (0..part_count)
                 .map(|_| {
                     (
-                        random_circle_point(),
+                        random_circle_point(rng),
                         Entity {
                             owner_id: None,
                             color: Color::rgb(
-                                global_rng().gen_range(0.0..=Self::COLOR_OFF),
-                                global_rng().gen_range(1.0 - Self::COLOR_OFF..=1.0),
-                                global_rng().gen_range(0.0..=Self::COLOR_OFF),
+                                Self::color_off(color.r, rng),
+                                Self::color_off(color.g, rng),
+                                Self::color_off(color.b, rng),
                             ),
                             pos: pos,
+                            prev_pos: pos,
                             vel: vec2(0.0, 0.0),
                             size: size / (part_count as f32).sqrt(),
                         },
@@ -54,22 +100,33 @@ impl Food {
         }
     }
 
+    /// Moves a color channel towards the middle by a random amount, so that parts differ.
+    fn color_off<R: Rng>(value: f32, rng: &mut R) -> f32 {
+        let off = rng.gen_range(0.0..=Self::COLOR_OFF);
+        if value > 0.5 {
+            value - off
+        } else {
+            value + off
+        }
+    }
+
     pub fn update(&mut self, delta_time: f32) {
         self.time = (self.time + delta_time * 3.0).min(1.0);
         for &mut (pos, ref mut part) in &mut self.parts {
+            part.prev_pos = part.pos;
             part.pos =
                 self.entity.pos + self.entity.size * pos * (1.0 - (1.0 - self.time).powf(2.0));
         }
     }
 
-    pub fn draw(&self, particles: &mut Vec<ParticleInstance>) {
+    pub fn draw(&self, alpha: f32, particles: &mut Vec<ParticleInstance>) {
         for &(_, ref part) in &self.parts {
             particles.push(ParticleInstance {
-                i_pos: part.pos,
+                i_pos: part.render_pos(alpha),
                 i_color: mix(Color::BLACK, part.color),
                 i_size: part.size * 1.1,
             });
-            part.draw(particles);
+            part.draw(alpha, particles);
         }
     }
 }
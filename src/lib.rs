@@ -0,0 +1,66 @@
+use geng::prelude::*;
+
+mod bindings;
+mod boss;
+mod config;
+mod entity;
+mod focus;
+mod food;
+mod map;
+mod mass;
+mod net;
+mod player;
+mod projectile;
+mod replay;
+#[cfg(not(target_arch = "wasm32"))]
+mod server;
+mod settings;
+mod spatial;
+mod waves;
+mod weapon;
+mod world;
+
+pub use bindings::*;
+pub use boss::*;
+pub use config::*;
+pub use entity::*;
+pub use focus::*;
+pub use food::*;
+pub use map::*;
+pub use mass::*;
+pub use net::*;
+pub use player::*;
+pub use projectile::*;
+pub use replay::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use server::*;
+pub use settings::*;
+pub use spatial::*;
+pub use waves::*;
+pub use weapon::*;
+pub use world::*;
+
+fn mix(a: Color<f32>, b: Color<f32>) -> Color<f32> {
+    Color::rgba(
+        (a.r + b.r) / 2.0,
+        (a.g + b.g) / 2.0,
+        (a.b + b.b) / 2.0,
+        (a.a + b.a) / 2.0,
+    )
+}
+
+fn random_circle_point<R: Rng>(rng: &mut R) -> Vec2<f32> {
+    loop {
+        let result = vec2(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+        if result.len() < 1.0 {
+            return result;
+        }
+    }
+}
+
+#[derive(ugli::Vertex, Debug)]
+pub struct ParticleInstance {
+    pub i_pos: Vec2<f32>,
+    pub i_size: f32,
+    pub i_color: Color<f32>,
+}
@@ -2,61 +2,53 @@
 
 use geng::prelude::*;
 
-mod entity;
-mod food;
-mod player;
-mod projectile;
-
-use entity::*;
-use food::*;
-use player::*;
-use projectile::*;
-
-static mut CAMERA_POS: Vec2<f32> = Vec2 { x: 0.0, y: 0.0 };
+use lifeshot::*;
 
 struct Sound {
     inner: RefCell<geng::SoundEffect>,
 }
 
 impl Sound {
-    fn set_pos(&self, pos: Vec2<f32>) {
-        let volume = clamp(
-            1.0 - (Game::delta_pos(pos, unsafe { CAMERA_POS }).len() / Game::CAMERA_FOV / 2.0)
-                .powf(2.0),
+    /// Sets the volume by how close `pos` is to the closest of the `listeners`,
+    /// `volume` at most.
+    fn set_pos(&self, pos: Vec2<f32>, arena: &ArenaConfig, listeners: &[Vec2<f32>], volume: f32) {
+        let distance = listeners
+            .iter()
+            .map(|&listener| arena.delta_pos(pos, listener).len())
+            .fold(f32::INFINITY, f32::min);
+        let closeness = clamp(
+            1.0 - (distance / Game::CAMERA_FOV / 2.0).powf(2.0),
             0.0..=1.0,
         );
-        self.inner.borrow_mut().set_volume(volume as f64 * 0.2);
+        self.inner
+            .borrow_mut()
+            .set_volume((volume * closeness) as f64 * 0.2);
     }
     fn stop(&self) {
         self.inner.borrow_mut().pause();
     }
 }
 
-fn play_sound(sound: &geng::Sound, pos: Vec2<f32>) -> Sound {
+fn play_sound(
+    sound: &geng::Sound,
+    pos: Vec2<f32>,
+    arena: &ArenaConfig,
+    listeners: &[Vec2<f32>],
+    volume: f32,
+) -> Sound {
     let sound = Sound {
         inner: RefCell::new(sound.play()),
     };
-    sound.set_pos(pos);
+    sound.set_pos(pos, arena, listeners, volume);
     sound
 }
 
-fn mix(a: Color<f32>, b: Color<f32>) -> Color<f32> {
-    Color::rgba(
-        (a.r + b.r) / 2.0,
-        (a.g + b.g) / 2.0,
-        (a.b + b.b) / 2.0,
-        (a.a + b.a) / 2.0,
-    )
-}
-
-fn random_circle_point() -> Vec2<f32> {
-    let mut rng = global_rng();
-    loop {
-        let result = vec2(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
-        if result.len() < 1.0 {
-            return result;
-        }
-    }
+/// What a local player plays with.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum InputDevice {
+    Keyboard(KeyboardLayout),
+    #[cfg(not(target_arch = "wasm32"))]
+    Gamepad(GamepadId),
 }
 
 #[derive(ugli::Vertex)]
@@ -64,113 +56,1123 @@ struct QuadVertex {
     a_pos: Vec2<f32>,
 }
 
-#[derive(ugli::Vertex, Debug)]
-pub struct ParticleInstance {
-    i_pos: Vec2<f32>,
-    i_size: f32,
-    i_color: Color<f32>,
+#[derive(ugli::Vertex)]
+struct ObstacleVertex {
+    a_pos: Vec2<f32>,
+    a_color: Color<f32>,
 }
 
 #[derive(geng::Assets)]
 struct Assets {
     aim: geng::Sound,
+    boss: geng::Sound,
     death: geng::Sound,
     heal: geng::Sound,
     hit: geng::Sound,
+    #[asset(path = "config.json")]
+    config: String,
+    #[asset(path = "waves.json")]
+    waves: String,
+    #[asset(path = "map.json")]
+    map: String,
     #[asset(path = "music.ogg")]
     music: geng::Sound,
     shoot: geng::Sound,
 }
 
+/// Rebinding the keyboard and mouse controls, shown over the menu.
+#[derive(Default)]
+struct BindingsScreen {
+    selected: usize,
+    /// Waiting for the key or button to bind the selected control to.
+    waiting: bool,
+    /// What the last change did.
+    message: Option<String>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum PauseItem {
+    Resume,
+    Restart,
+    Settings,
+    Quit,
+}
+
+impl PauseItem {
+    const ALL: [Self; 4] = [
+        PauseItem::Resume,
+        PauseItem::Restart,
+        PauseItem::Settings,
+        PauseItem::Quit,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            PauseItem::Resume => "resume",
+            PauseItem::Restart => "restart",
+            PauseItem::Settings => "settings",
+            PauseItem::Quit => "quit to menu",
+        }
+    }
+}
+
+/// Shown over the match while it is paused.
+#[derive(Default)]
+struct PauseMenu {
+    selected: usize,
+    /// Asking whether to really throw the match away.
+    confirm_restart: bool,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum SettingsItem {
+    MusicVolume,
+    SfxVolume,
+    Fullscreen,
+    Bindings,
+    Palette,
+}
+
+impl SettingsItem {
+    const ALL: [Self; 5] = [
+        SettingsItem::MusicVolume,
+        SettingsItem::SfxVolume,
+        SettingsItem::Fullscreen,
+        SettingsItem::Bindings,
+        SettingsItem::Palette,
+    ];
+}
+
+/// Expanding ring left by a dead boss.
+struct Shockwave {
+    pos: Vec2<f32>,
+    time: f32,
+}
+
+struct Notice {
+    text: String,
+    color: Color<f32>,
+    time_left: f32,
+}
+
 pub struct Game {
     assets: Assets,
     context: Rc<Geng>,
     font: geng::Font,
-    players: Vec<Player>,
-    projectiles: Vec<Projectile>,
-    food: Vec<Food>,
-    next_food: f32,
-    camera_pos: Vec2<f32>,
+    config: Config,
+    waves: WaveScript,
+    map: Map,
+    #[cfg(not(target_arch = "wasm32"))]
+    config_watcher: ConfigWatcher,
+    config_notice: Option<Notice>,
+    show_mass: bool,
+    /// Difficulty for the next match.
+    difficulty: Difficulty,
+    /// Arena edges for the next match, overriding the config's.
+    topology: WorldTopology,
+    /// Controls of every local player for the next match, one at least.
+    inputs: Vec<InputDevice>,
+    /// Players controlled on this machine in a local match or replay.
+    local_ids: Vec<usize>,
+    #[cfg(not(target_arch = "wasm32"))]
+    gamepads: Rc<RefCell<Gamepads>>,
+    /// The start menu is shown instead of the match.
+    menu: bool,
+    bindings: Rc<RefCell<Bindings>>,
+    bindings_screen: Option<BindingsScreen>,
+    settings: Settings,
+    /// Selected item of the settings screen, if it is open.
+    settings_screen: Option<usize>,
+    /// The match is paused, unless it is played online.
+    pause: Option<PauseMenu>,
+    /// Started by the first key or click, browsers allow no sound before.
+    music: Option<geng::SoundEffect>,
+    world: World,
+    tick_duration: f32,
+    accumulated_time: f32,
+    recording: Replay,
+    playback: Option<Rc<Replay>>,
+    desynced: bool,
+    replay_message: Option<String>,
+    net: Option<NetClient>,
+    aim_sounds: HashMap<usize, Sound>,
+    /// Where every view looks, one per local player.
+    cameras: Vec<Vec2<f32>>,
     quad_geometry: ugli::VertexBuffer<QuadVertex>,
     particle_instances: ugli::VertexBuffer<ParticleInstance>,
     particle_program: ugli::Program,
+    obstacle_geometry: ugli::VertexBuffer<ObstacleVertex>,
+    obstacle_program: ugli::Program,
     background_particles: Vec<Entity>,
+    shockwaves: Vec<Shockwave>,
     mouse_pos: Rc<Cell<Vec2<f32>>>,
-    next_wave_timer: f32,
-    next_wave: usize,
-    time_played: f32,
-    kills: usize,
-    waves_finished: usize,
-    start: f32,
+    focus: Focus,
 }
 
 impl Game {
     const CAMERA_FOV: f32 = 15.0;
-    const START: f32 = 5.0;
+    const MAX_LOCAL_PLAYERS: usize = 4;
+    #[cfg(not(target_arch = "wasm32"))]
+    const BINDINGS_PATH: &'static str = "bindings.json";
+    #[cfg(not(target_arch = "wasm32"))]
+    const SETTINGS_PATH: &'static str = "settings.json";
+    const VOLUME_STEP: f32 = 0.1;
+    /// A frame this long means the window was hidden or minimized.
+    const HIDDEN_FRAME_TIME: f32 = 0.5;
+    const MAX_FRAME_TIME: f32 = 0.25;
+    const NOTICE_TIME: f32 = 3.0;
+    const SHOCKWAVE_TIME: f32 = 1.5;
+    const SHOCKWAVE_SPEED: f32 = 20.0;
 
-    const MAX_FOOD: usize = 100;
-    const FOOD_K: f32 = 3.0;
-    const FOOD_SIZE: Range<f32> = 0.1..0.5;
-    const FOOD_SPAWN: Range<f32> = 0.05..0.1;
+    fn reset(&mut self) {
+        for (_, sound) in self.aim_sounds.drain() {
+            sound.stop();
+        }
+        let seed = global_rng().gen();
+        let mut config = self.config.clone();
+        config.arena.topology = self.topology;
+        self.tick_duration = config.tick_duration();
+        #[cfg(not(target_arch = "wasm32"))]
+        self.gamepads.borrow_mut().claim(
+            self.inputs
+                .iter()
+                .filter_map(|&input| match input {
+                    InputDevice::Gamepad(id) => Some(id),
+                    _ => None,
+                })
+                .collect(),
+        );
+        let controllers: Vec<_> = self
+            .inputs
+            .iter()
+            .map(|&input| self.controller(input))
+            .collect();
+        self.world = World::empty(seed, config.clone());
+        self.local_ids = controllers
+            .into_iter()
+            .map(|controller| self.world.add_player(controller))
+            .collect();
+        self.cameras = vec![vec2(0.0, 0.0); self.local_ids.len()];
+        self.world.difficulty = self.difficulty;
+        self.world.waves = self.waves.clone();
+        self.world.map = self.map.clone();
+        self.recording = Replay::new(
+            seed,
+            config,
+            self.difficulty,
+            self.waves.clone(),
+            self.map.clone(),
+            self.local_ids.len(),
+        );
+        self.shockwaves.clear();
+        self.playback = None;
+        self.replay_message = None;
+    }
 
-    const TIME_BETWEEN_WAVES: f32 = 120.0;
+    fn play(&mut self, replay: Rc<Replay>) {
+        for (_, sound) in self.aim_sounds.drain() {
+            sound.stop();
+        }
+        self.tick_duration = replay.config.tick_duration();
+        self.world = World::empty(replay.seed, replay.config.clone());
+        self.local_ids = (0..replay.players)
+            .map(|_| self.world.add_player(ReplayController::new(&replay)))
+            .collect();
+        self.cameras = vec![vec2(0.0, 0.0); self.local_ids.len()];
+        self.world.difficulty = replay.difficulty;
+        self.world.waves = replay.waves.clone();
+        self.world.map = replay.map.clone();
+        self.recording = Replay::new(
+            replay.seed,
+            replay.config.clone(),
+            replay.difficulty,
+            replay.waves.clone(),
+            replay.map.clone(),
+            replay.players,
+        );
+        self.shockwaves.clear();
+        self.playback = Some(replay);
+        self.desynced = false;
+        self.replay_message = None;
+    }
 
-    const WORLD_SIZE: f32 = 50.0;
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_replay(&mut self) {
+        let path = format!("lifeshot-{}.replay", self.recording.seed);
+        let result = std::fs::File::create(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|file| self.recording.write_to(std::io::BufWriter::new(file)));
+        self.replay_message = Some(match result {
+            Ok(()) => format!("replay saved to {}", path),
+            Err(e) => {
+                error!("Failed to save replay to {:?}: {}", path, e);
+                format!("failed to save replay: {}", e)
+            }
+        });
+    }
 
-    const WAVE_PAUSE: f32 = 10.0;
+    /// Applies changes to `config.json` to the running match.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn reload_config(&mut self, delta_time: f32) {
+        let result = match self.config_watcher.update(delta_time) {
+            Some(result) => result,
+            None => return,
+        };
+        self.config_notice = Some(match result {
+            Ok(config) => {
+                info!("Reloaded config");
+                if self.net.is_none() && self.playback.is_none() {
+                    // The arena was picked in the menu, it stays for the whole match
+                    let mut match_config = config.clone();
+                    match_config.arena.topology = self.world.config.arena.topology;
+                    self.tick_duration = match_config.tick_duration();
+                    self.recording.record_config(&match_config);
+                    self.world.config = match_config;
+                }
+                self.config = config;
+                Notice {
+                    text: "config reloaded".to_owned(),
+                    color: Color::rgba(0.5, 1.0, 0.5, 1.0),
+                    time_left: Self::NOTICE_TIME,
+                }
+            }
+            Err(e) => {
+                error!("Failed to reload config: {:#}", e);
+                Notice {
+                    text: format!("{:#}", e),
+                    color: Color::rgba(1.0, 0.5, 0.5, 1.0),
+                    time_left: f32::INFINITY,
+                }
+            }
+        });
+    }
 
-    const PROJECTILE_DEATH_SPEED: f32 = 0.1;
-    const PROJECTILE_STRENGTH: f32 = 0.5;
-    const PLAYER_DEATH_SPEED: f32 = 1.0 / 20.0;
+    fn controller(&self, input: InputDevice) -> Box<dyn Controller> {
+        match input {
+            InputDevice::Keyboard(layout) => Box::new(KeyboardController::new(
+                &self.context,
+                layout,
+                &self.bindings,
+                &self.mouse_pos,
+            )),
+            #[cfg(not(target_arch = "wasm32"))]
+            InputDevice::Gamepad(id) => Box::new(GamepadController::new(&self.gamepads, Some(id))),
+        }
+    }
 
-    fn delta_pos(a: Vec2<f32>, b: Vec2<f32>) -> Vec2<f32> {
-        let dv = b - a;
-        Self::normalize(dv)
+    /// Keys read by the keyboard layouts of the local players.
+    fn reserved_keys(&self) -> Vec<geng::Key> {
+        self.inputs
+            .iter()
+            .flat_map(|&input| match input {
+                InputDevice::Keyboard(layout) => layout.keys(),
+                #[cfg(not(target_arch = "wasm32"))]
+                InputDevice::Gamepad(_) => &[],
+            })
+            .copied()
+            .collect()
     }
 
-    fn normalize(mut v: Vec2<f32>) -> Vec2<f32> {
-        while v.x > Self::WORLD_SIZE {
-            v.x -= 2.0 * Self::WORLD_SIZE;
+    /// Devices to choose from in the menu, with their names.
+    fn input_devices(&self) -> Vec<(InputDevice, String)> {
+        let bindings = self.bindings.borrow();
+        #[allow(unused_mut)]
+        let mut devices: Vec<_> = [KeyboardLayout::KeyboardMouse, KeyboardLayout::Arrows]
+            .iter()
+            // A layout reading keys that are bound would move two players at once
+            .filter(|layout| {
+                layout
+                    .keys()
+                    .iter()
+                    .all(|&key| bindings.controls(Input::Key(key)).next().is_none())
+            })
+            .map(|&layout| (InputDevice::Keyboard(layout), layout.name().to_owned()))
+            .collect();
+        #[cfg(not(target_arch = "wasm32"))]
+        devices.extend(
+            self.gamepads
+                .borrow()
+                .connected()
+                .into_iter()
+                .map(|(id, name)| (InputDevice::Gamepad(id), name)),
+        );
+        devices
+    }
+
+    fn draw_menu(&self, framebuffer: &mut ugli::Framebuffer) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let font = &self.font;
+        let scale = framebuffer_size.y / 20.0;
+        let mid = framebuffer_size / 2.0;
+        font.draw_aligned(
+            framebuffer,
+            "LIFESHOT",
+            vec2(0.0, 6.5 * scale) + mid,
+            0.5,
+            scale * 3.0,
+            Color::rgba(0.5, 0.5, 1.0, 1.0),
+        );
+        font.draw_aligned(
+            framebuffer,
+            "choose difficulty",
+            vec2(0.0, 4.5 * scale) + mid,
+            0.5,
+            scale,
+            Color::rgba(0.5, 0.5, 0.5, 1.0),
+        );
+        for (i, &difficulty) in Difficulty::ALL.iter().enumerate() {
+            let (text, color) = if difficulty == self.difficulty {
+                (
+                    format!("> {} <", difficulty.name()),
+                    Color::rgba(1.0, 1.0, 1.0, 1.0),
+                )
+            } else {
+                (
+                    difficulty.name().to_owned(),
+                    Color::rgba(0.5, 0.5, 0.5, 1.0),
+                )
+            };
+            font.draw_aligned(
+                framebuffer,
+                &text,
+                vec2(0.0, 3.0 * scale - i as f32 * 1.2 * scale) + mid,
+                0.5,
+                scale,
+                color,
+            );
         }
-        while v.y > Self::WORLD_SIZE {
-            v.y -= 2.0 * Self::WORLD_SIZE;
+        font.draw_aligned(
+            framebuffer,
+            &format!("arena: < {} >", self.topology.name()),
+            vec2(0.0, -2.0 * scale) + mid,
+            0.5,
+            scale,
+            Color::rgba(1.0, 1.0, 1.0, 1.0),
+        );
+        let devices = self.input_devices();
+        for (i, input) in self.inputs.iter().enumerate() {
+            let name = match devices.iter().find(|(device, _)| device == input) {
+                Some((_, name)) => name.as_str(),
+                None => "gamepad (unplugged)",
+            };
+            font.draw_aligned(
+                framebuffer,
+                &format!("player {}: < {} >", i + 1, name),
+                vec2(0.0, -3.5 * scale - i as f32 * scale) + mid,
+                0.5,
+                scale * 0.8,
+                Color::rgba(1.0, 1.0, 1.0, 1.0),
+            );
         }
-        while v.x < -Self::WORLD_SIZE {
-            v.x += 2.0 * Self::WORLD_SIZE;
+        font.draw_aligned(
+            framebuffer,
+            "W/S to choose, A/D to change arena, Enter to start",
+            vec2(0.0, -8.5 * scale) + mid,
+            0.5,
+            scale * 0.6,
+            Color::rgba(0.5, 0.5, 0.5, 1.0),
+        );
+        font.draw_aligned(
+            framebuffer,
+            "Q/E for fewer or more players, 1-4 to change their controls, B for key bindings",
+            vec2(0.0, -9.3 * scale) + mid,
+            0.5,
+            scale * 0.6,
+            Color::rgba(0.5, 0.5, 0.5, 1.0),
+        );
+    }
+
+    /// The next device after `input`, going around.
+    fn next_input(&self, input: InputDevice) -> InputDevice {
+        let devices = self.input_devices();
+        let next = devices
+            .iter()
+            .position(|&(device, _)| device == input)
+            .map_or(0, |index| (index + 1) % devices.len());
+        devices[next].0
+    }
+
+    fn handle_menu_event(&mut self, event: geng::Event) {
+        let fullscreen = Input::pressed_by(&event) == Some(self.bindings.borrow().fullscreen);
+        let index = Difficulty::ALL
+            .iter()
+            .position(|&d| d == self.difficulty)
+            .unwrap();
+        if let geng::Event::KeyDown { key } = event {
+            match key {
+                geng::Key::W | geng::Key::Up => {
+                    self.difficulty = Difficulty::ALL[index.max(1) - 1];
+                }
+                geng::Key::S | geng::Key::Down => {
+                    self.difficulty = Difficulty::ALL[(index + 1).min(Difficulty::ALL.len() - 1)];
+                }
+                geng::Key::A | geng::Key::D | geng::Key::Left | geng::Key::Right => {
+                    let index = WorldTopology::ALL
+                        .iter()
+                        .position(|&t| t == self.topology)
+                        .unwrap();
+                    self.topology = WorldTopology::ALL[(index + 1) % WorldTopology::ALL.len()];
+                }
+                geng::Key::Q if self.inputs.len() > 1 => {
+                    self.inputs.pop();
+                }
+                geng::Key::E if self.inputs.len() < Self::MAX_LOCAL_PLAYERS => {
+                    // Whatever nobody plays with yet, the arrow keys if everything is taken
+                    let devices: Vec<_> = self
+                        .input_devices()
+                        .into_iter()
+                        .map(|(device, _)| device)
+                        .collect();
+                    let arrows = InputDevice::Keyboard(KeyboardLayout::Arrows);
+                    let input = devices
+                        .iter()
+                        .copied()
+                        .find(|device| !self.inputs.contains(device))
+                        .or_else(|| Some(arrows).filter(|arrows| devices.contains(arrows)));
+                    if let Some(input) = input {
+                        self.inputs.push(input);
+                    }
+                }
+                geng::Key::Num1 | geng::Key::Num2 | geng::Key::Num3 | geng::Key::Num4 => {
+                    let player = match key {
+                        geng::Key::Num1 => 0,
+                        geng::Key::Num2 => 1,
+                        geng::Key::Num3 => 2,
+                        _ => 3,
+                    };
+                    if let Some(&input) = self.inputs.get(player) {
+                        self.inputs[player] = self.next_input(input);
+                    }
+                }
+                geng::Key::Enter | geng::Key::Space => {
+                    self.menu = false;
+                    self.reset();
+                }
+                geng::Key::B => self.bindings_screen = Some(default()),
+                _ if fullscreen => self.context.window().toggle_fullscreen(),
+                _ => {}
+            }
         }
-        while v.y < -Self::WORLD_SIZE {
-            v.y += 2.0 * Self::WORLD_SIZE;
+    }
+
+    /// Bindings saved earlier, the default ones if there are none.
+    fn load_bindings() -> anyhow::Result<Bindings> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if std::path::Path::new(Self::BINDINGS_PATH).exists() {
+                return Bindings::load(Self::BINDINGS_PATH);
+            }
         }
-        v
+        Ok(default())
     }
 
-    fn reset(&mut self) {
-        self.players = vec![Player::new(
-            vec2(0.0, 0.0),
-            Color::rgb(0.5, 0.5, 1.0),
-            KeyboardController::new(&self.context, &self.mouse_pos),
-            0,
-        )];
-        self.food = Vec::new();
-        self.next_food = 0.0;
-        self.projectiles = Vec::new();
-        self.next_wave_timer = Self::WAVE_PAUSE;
-        self.next_wave = 1;
-        self.start = 0.0;
-        self.kills = 0;
-        self.waves_finished = 0;
-        self.time_played = 0.0;
-    }
-
-    fn new(context: &Rc<Geng>, mut assets: Assets) -> Self {
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_bindings(&mut self) {
+        if let Err(e) = self.bindings.borrow().save(Self::BINDINGS_PATH) {
+            error!("Failed to save bindings: {:#}", e);
+            self.config_notice = Some(Self::error_notice(&e));
+        }
+    }
+
+    /// Settings saved earlier, the default ones if there are none.
+    fn load_settings() -> anyhow::Result<Settings> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            if std::path::Path::new(Self::SETTINGS_PATH).exists() {
+                return Settings::load(Self::SETTINGS_PATH);
+            }
+        }
+        Ok(default())
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_settings(&mut self) {
+        if let Err(e) = self.settings.save(Self::SETTINGS_PATH) {
+            error!("Failed to save settings: {:#}", e);
+            self.config_notice = Some(Self::error_notice(&e));
+        }
+    }
+
+    fn error_notice(e: &anyhow::Error) -> Notice {
+        Notice {
+            text: format!("{:#}", e),
+            color: Color::rgba(1.0, 0.5, 0.5, 1.0),
+            time_left: Self::NOTICE_TIME,
+        }
+    }
+
+    /// A title over a list to choose from, with the `selected` item marked.
+    fn draw_choices(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        title: &str,
+        items: &[String],
+        selected: usize,
+        message: Option<&str>,
+        hint: &str,
+    ) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let font = &self.font;
+        let scale = framebuffer_size.y / 20.0;
+        let mid = framebuffer_size / 2.0;
+        font.draw_aligned(
+            framebuffer,
+            title,
+            vec2(0.0, 6.5 * scale) + mid,
+            0.5,
+            scale * 2.0,
+            Color::rgba(0.5, 0.5, 1.0, 1.0),
+        );
+        // Long lists squeeze together so they stay clear of the message and hint
+        let step = (10.0 / items.len() as f32).min(1.2);
+        for (i, item) in items.iter().enumerate() {
+            let (text, color) = if i == selected {
+                (format!("> {} <", item), Color::rgba(1.0, 1.0, 1.0, 1.0))
+            } else {
+                (item.clone(), Color::rgba(0.5, 0.5, 0.5, 1.0))
+            };
+            font.draw_aligned(
+                framebuffer,
+                &text,
+                vec2(0.0, 4.0 * scale - i as f32 * step * scale) + mid,
+                0.5,
+                scale * step / 1.2,
+                color,
+            );
+        }
+        if let Some(message) = message {
+            font.draw_aligned(
+                framebuffer,
+                message,
+                vec2(0.0, -6.5 * scale) + mid,
+                0.5,
+                scale * 0.7,
+                Color::rgba(1.0, 1.0, 0.5, 1.0),
+            );
+        }
+        font.draw_aligned(
+            framebuffer,
+            hint,
+            vec2(0.0, -8.5 * scale) + mid,
+            0.5,
+            scale * 0.6,
+            Color::rgba(0.5, 0.5, 0.5, 1.0),
+        );
+    }
+
+    fn draw_bindings_screen(&self, framebuffer: &mut ugli::Framebuffer, screen: &BindingsScreen) {
+        let bindings = self.bindings.borrow();
+        let items: Vec<_> = Control::ALL
+            .iter()
+            .enumerate()
+            .map(|(i, &control)| {
+                let input = if screen.waiting && i == screen.selected {
+                    "...".to_owned()
+                } else {
+                    bindings.get(control).name()
+                };
+                format!("{}: {}", control.name(), input)
+            })
+            .collect();
+        let hint = if screen.waiting {
+            "press a key or mouse button, Backspace to cancel"
+        } else {
+            "W/S to choose, Enter to rebind, Backspace for defaults, Escape to go back"
+        };
+        self.draw_choices(
+            framebuffer,
+            "KEY BINDINGS",
+            &items,
+            screen.selected,
+            screen.message.as_deref(),
+            hint,
+        );
+    }
+
+    fn draw_pause_menu(&self, framebuffer: &mut ugli::Framebuffer, menu: &PauseMenu) {
+        let items: Vec<_> = PauseItem::ALL
+            .iter()
+            .map(|item| item.name().to_owned())
+            .collect();
+        let (message, hint) = if menu.confirm_restart {
+            (
+                Some("restart and lose this match?"),
+                "Enter to restart, Escape to go back",
+            )
+        } else {
+            let message = if self.net.is_some() {
+                Some("the match goes on online")
+            } else {
+                None
+            };
+            (message, "W/S to choose, Enter to select, Escape to resume")
+        };
+        self.draw_choices(framebuffer, "PAUSED", &items, menu.selected, message, hint);
+    }
+
+    fn draw_settings_screen(&self, framebuffer: &mut ugli::Framebuffer, selected: usize) {
+        let percent = |volume: f32| (volume * 100.0).round() as i32;
+        let items: Vec<_> = SettingsItem::ALL
+            .iter()
+            .map(|item| match item {
+                SettingsItem::MusicVolume => {
+                    format!("music volume: < {}% >", percent(self.settings.music_volume))
+                }
+                SettingsItem::SfxVolume => {
+                    format!("sound volume: < {}% >", percent(self.settings.sfx_volume))
+                }
+                SettingsItem::Fullscreen => format!(
+                    "fullscreen: {}",
+                    if self.context.window().is_fullscreen() {
+                        "on"
+                    } else {
+                        "off"
+                    },
+                ),
+                SettingsItem::Bindings => "key bindings".to_owned(),
+                SettingsItem::Palette => {
+                    format!("colours: < {} >", self.settings.palette.name())
+                }
+            })
+            .collect();
+        self.draw_choices(
+            framebuffer,
+            "SETTINGS",
+            &items,
+            selected,
+            None,
+            "W/S to choose, A/D to change, Enter to select, Escape to go back",
+        );
+    }
+
+    fn handle_bindings_event(&mut self, event: geng::Event) {
+        let reserved = self.reserved_keys();
+        let screen = self.bindings_screen.as_mut().unwrap();
+        if screen.waiting {
+            if let geng::Event::KeyDown {
+                key: geng::Key::Backspace,
+            } = event
+            {
+                screen.waiting = false;
+                return;
+            }
+            let input = match Input::pressed_by(&event) {
+                Some(input) => input,
+                None => return,
+            };
+            let control = Control::ALL[screen.selected];
+            screen.waiting = false;
+            let mut bindings = self.bindings.borrow_mut();
+            let old = bindings.get(control);
+            screen.message = Some(match bindings.bind(control, input, &reserved) {
+                Ok(others) if others.is_empty() => {
+                    format!("{} is on {}", control.name(), input.name())
+                }
+                // Never two controls on the same key, the others get the old key instead
+                Ok(others) => format!(
+                    "{} {} on {}, moved to {}",
+                    others
+                        .iter()
+                        .map(|other| other.name())
+                        .collect::<Vec<_>>()
+                        .join(" and "),
+                    if others.len() > 1 { "were" } else { "was" },
+                    input.name(),
+                    old.name(),
+                ),
+                Err(e) => format!("{:#}", e),
+            });
+            if bindings.get(control) == old {
+                return;
+            }
+        } else if let geng::Event::KeyDown { key } = event {
+            match key {
+                geng::Key::W | geng::Key::Up => screen.selected = screen.selected.max(1) - 1,
+                geng::Key::S | geng::Key::Down => {
+                    screen.selected = (screen.selected + 1).min(Control::ALL.len() - 1)
+                }
+                geng::Key::Enter | geng::Key::Space => screen.waiting = true,
+                geng::Key::Backspace => {
+                    *self.bindings.borrow_mut() = default();
+                    screen.message = Some("default bindings restored".to_owned());
+                }
+                geng::Key::Escape => self.bindings_screen = None,
+                _ => {}
+            }
+            if key != geng::Key::Backspace {
+                return;
+            }
+        } else {
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.save_bindings();
+    }
+
+    fn handle_settings_event(&mut self, event: geng::Event) {
+        let mut selected = self.settings_screen.unwrap();
+        let key = match event {
+            geng::Event::KeyDown { key } => key,
+            _ => return,
+        };
+        let step = match key {
+            geng::Key::A | geng::Key::Left => -1.0,
+            geng::Key::D | geng::Key::Right => 1.0,
+            _ => 0.0,
+        };
+        let enter = key == geng::Key::Enter || key == geng::Key::Space;
+        match key {
+            geng::Key::W | geng::Key::Up => selected = selected.max(1) - 1,
+            geng::Key::S | geng::Key::Down => {
+                selected = (selected + 1).min(SettingsItem::ALL.len() - 1)
+            }
+            geng::Key::Escape => {
+                self.settings_screen = None;
+                return;
+            }
+            _ => {}
+        }
+        self.settings_screen = Some(selected);
+        let changed = match SettingsItem::ALL[selected] {
+            SettingsItem::MusicVolume | SettingsItem::SfxVolume if step != 0.0 => {
+                let volume = if SettingsItem::ALL[selected] == SettingsItem::MusicVolume {
+                    &mut self.settings.music_volume
+                } else {
+                    &mut self.settings.sfx_volume
+                };
+                *volume = clamp(
+                    ((*volume + step * Self::VOLUME_STEP) * 10.0).round() / 10.0,
+                    0.0..=1.0,
+                );
+                true
+            }
+            SettingsItem::Fullscreen if enter || step != 0.0 => {
+                self.context.window().toggle_fullscreen();
+                false
+            }
+            SettingsItem::Bindings if enter => {
+                self.bindings_screen = Some(default());
+                false
+            }
+            SettingsItem::Palette if enter || step != 0.0 => {
+                self.settings.palette = self.settings.palette.next();
+                true
+            }
+            _ => false,
+        };
+        if changed {
+            let volume = self.music_volume();
+            if let Some(ref mut music) = self.music {
+                music.set_volume(volume);
+            }
+            #[cfg(not(target_arch = "wasm32"))]
+            self.save_settings();
+        }
+    }
+
+    fn handle_pause_event(&mut self, event: geng::Event) {
+        let input = Input::pressed_by(&event);
+        let (pause, fullscreen) = {
+            let bindings = self.bindings.borrow();
+            (bindings.pause, bindings.fullscreen)
+        };
+        let key = match event {
+            geng::Event::KeyDown { key } => Some(key),
+            _ => None,
+        };
+        let menu = self.pause.as_mut().unwrap();
+        if menu.confirm_restart {
+            match key {
+                Some(geng::Key::Enter) | Some(geng::Key::Space) => self.restart(),
+                Some(geng::Key::Escape) | Some(geng::Key::Backspace) => {
+                    menu.confirm_restart = false
+                }
+                _ => {}
+            }
+            return;
+        }
+        match key {
+            Some(geng::Key::W) | Some(geng::Key::Up) => menu.selected = menu.selected.max(1) - 1,
+            Some(geng::Key::S) | Some(geng::Key::Down) => {
+                menu.selected = (menu.selected + 1).min(PauseItem::ALL.len() - 1)
+            }
+            Some(geng::Key::Enter) | Some(geng::Key::Space) => {
+                match PauseItem::ALL[menu.selected] {
+                    PauseItem::Resume => self.pause = None,
+                    PauseItem::Restart => menu.confirm_restart = true,
+                    PauseItem::Settings => self.settings_screen = Some(0),
+                    PauseItem::Quit => self.quit(),
+                }
+            }
+            Some(geng::Key::Escape) => self.pause = None,
+            _ if input == Some(pause) => self.pause = None,
+            _ if input == Some(fullscreen) => self.context.window().toggle_fullscreen(),
+            _ => {}
+        }
+    }
+
+    fn open_pause(&mut self) {
+        for (_, sound) in self.aim_sounds.drain() {
+            sound.stop();
+        }
+        self.pause = Some(default());
+    }
+
+    /// Pauses a running local match, as if the bound key was pressed.
+    fn auto_pause(&mut self) {
+        if !self.menu
+            && self.pause.is_none()
+            && self.net.is_none()
+            && self.playback.is_none()
+            && self.world.tick > 0
+            && self.local_alive()
+        {
+            info!("Window hidden or out of focus, pausing");
+            self.open_pause();
+        }
+    }
+
+    /// Whether the world stands still.
+    fn paused(&self) -> bool {
+        self.pause.is_some() && self.net.is_none()
+    }
+
+    fn restart(&mut self) {
+        self.pause = None;
+        let player_alive = self.local_alive();
+        match self.net {
+            Some(ref mut net) => {
+                if !player_alive {
+                    net.respawn();
+                }
+            }
+            None => self.reset(),
+        }
+    }
+
+    /// Leaves the match for the start menu.
+    fn quit(&mut self) {
+        for (_, sound) in self.aim_sounds.drain() {
+            sound.stop();
+        }
+        self.pause = None;
+        self.net = None;
+        self.playback = None;
+        self.menu = true;
+    }
+
+    fn music_volume(&self) -> f64 {
+        self.settings.music_volume as f64 * 0.2
+    }
+
+    /// Browsers only allow sound after the first key or click.
+    fn start_music(&mut self) {
+        if self.music.is_none() {
+            let mut music = self.assets.music.play();
+            music.set_volume(self.music_volume());
+            self.music = Some(music);
+        }
+    }
+
+    fn obstacle_color(response: ProjectileResponse) -> Color<f32> {
+        match response {
+            ProjectileResponse::Bounce => Color::rgb(0.3, 0.3, 0.35),
+            ProjectileResponse::Absorb => Color::rgb(0.25, 0.1, 0.15),
+        }
+    }
+
+    /// Debug view of where mass comes from and goes to.
+    fn draw_mass_overlay(&self, framebuffer: &mut ugli::Framebuffer) {
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let scale = framebuffer_size.y / 40.0;
+        let ledger = &self.world.ledger;
+        let world_mass = self.world.total_mass();
+        let mut lines = vec![
+            format!(
+                "mass {:.3}, drift {:.5}",
+                world_mass,
+                ledger.drift(world_mass),
+            ),
+            format!(
+                "last tick +{:.5} -{:.5}",
+                ledger.tick.created(),
+                ledger.tick.destroyed(),
+            ),
+        ];
+        lines.extend(mass_balance_lines("match", &ledger.total));
+        for (i, id) in self.local_ids().into_iter().enumerate() {
+            if let Some(balance) = ledger.players.get(&id) {
+                lines.extend(mass_balance_lines(&format!("player {}", i + 1), balance));
+            }
+        }
+        for (i, line) in lines.iter().enumerate() {
+            self.font.draw_aligned(
+                framebuffer,
+                line,
+                vec2(scale, framebuffer_size.y - (i + 2) as f32 * scale),
+                0.0,
+                scale,
+                Color::rgba(1.0, 1.0, 1.0, 0.7),
+            );
+        }
+    }
+
+    /// Mass of the first boss alive, against the mass it came with.
+    fn draw_boss_bar(&self, framebuffer: &mut ugli::Framebuffer) {
+        let boss = match self
+            .world
+            .players
+            .iter()
+            .find(|player| player.archetype == Some(Archetype::Boss))
+        {
+            Some(boss) => boss,
+            None => return,
+        };
+        let config = &self.world.config;
+        let full_mass = (config.player.initial_size * config.archetypes.boss.size).powf(2.0);
+        let fraction = clamp(boss.mass() / full_mass, 0.0..=1.0);
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        let scale = framebuffer_size.y / 20.0;
+        let size = vec2(framebuffer_size.x * 0.4, scale * 0.3);
+        let pos = vec2(
+            (framebuffer_size.x - size.x) / 2.0,
+            framebuffer_size.y / 2.0 + 6.5 * scale,
+        );
+        let draw_2d = self.context.draw_2d();
+        draw_2d.quad(
+            framebuffer,
+            &geng::PixelPerfectCamera,
+            AABB::from_corners(pos, pos + size),
+            Color::rgba(1.0, 1.0, 1.0, 0.2),
+        );
+        draw_2d.quad(
+            framebuffer,
+            &geng::PixelPerfectCamera,
+            AABB::from_corners(pos, pos + vec2(size.x * fraction, size.y)),
+            boss.color,
+        );
+        self.font.draw_aligned(
+            framebuffer,
+            "BOSS",
+            vec2(framebuffer_size.x / 2.0, pos.y + size.y + scale * 0.2),
+            0.5,
+            scale * 0.5,
+            Color::rgba(1.0, 1.0, 1.0, 0.5),
+        );
+    }
+
+    /// A local player's weapon, in the bottom right corner of their view.
+    fn draw_weapon(&self, framebuffer: &mut ugli::Framebuffer, view: AABB<f32>, player: &Player) {
+        let scale = framebuffer.size().y as f32 / 40.0;
+        let index = Weapon::ALL
+            .iter()
+            .position(|&weapon| weapon == player.weapon)
+            .unwrap();
+        self.font.draw_aligned(
+            framebuffer,
+            &format!("{} - {}", index + 1, player.weapon.name()),
+            vec2(view.x_max - scale, view.y_min + scale * 1.2),
+            1.0,
+            scale,
+            Color::rgba(1.0, 1.0, 1.0, 0.7),
+        );
+    }
+
+    /// Status effects on a local player, with seconds left,
+    /// in the bottom left corner of their view.
+    fn draw_status_effects(
+        &self,
+        framebuffer: &mut ugli::Framebuffer,
+        view: AABB<f32>,
+        player: &Player,
+    ) {
+        let scale = framebuffer.size().y as f32 / 40.0;
+        for (i, effect) in player.status_effects.iter().enumerate() {
+            let stacks = if effect.stacks > 1 {
+                format!(" x{}", effect.stacks)
+            } else {
+                String::new()
+            };
+            self.font.draw_aligned(
+                framebuffer,
+                &format!(
+                    "{}{} {}",
+                    effect.kind.name(),
+                    stacks,
+                    f32::ceil(effect.time_left)
+                ),
+                vec2(
+                    view.x_min + scale,
+                    view.y_min + (i + 1) as f32 * scale * 1.2,
+                ),
+                0.0,
+                scale,
+                effect.kind.color(),
+            );
+        }
+    }
+
+    fn new(
+        context: &Rc<Geng>,
+        mut assets: Assets,
+        replay: Option<Replay>,
+        connection: Option<Connection>,
+    ) -> Self {
         assets.music.looped = true;
+        let config = match Config::parse(&assets.config) {
+            Ok(config) => config,
+            Err(e) => panic!("Invalid config.json: {:#}", e),
+        };
+        let waves = match WaveScript::parse(&assets.waves) {
+            Ok(waves) => waves,
+            Err(e) => panic!("Invalid waves.json: {:#}", e),
+        };
+        let map = match Map::parse(&assets.map) {
+            Ok(map) => map,
+            Err(e) => panic!("Invalid map.json: {:#}", e),
+        };
+        let mut config_notice = None;
+        let bindings = Self::load_bindings().unwrap_or_else(|e| {
+            error!("Failed to load bindings: {:#}", e);
+            config_notice = Some(Self::error_notice(&e));
+            default()
+        });
+        let settings = Self::load_settings().unwrap_or_else(|e| {
+            error!("Failed to load settings: {:#}", e);
+            config_notice = Some(Self::error_notice(&e));
+            default()
+        });
+        let bindings = Rc::new(RefCell::new(bindings));
+        let mouse_pos = Rc::new(Cell::new(vec2(0.0, 0.0)));
+        let seed = global_rng().gen();
         let mut game = Self {
             assets,
             context: context.clone(),
-            players: Vec::new(),
-            food: Vec::new(),
-            next_food: 0.0,
-            projectiles: Vec::new(),
+            world: World::new(
+                seed,
+                config.clone(),
+                KeyboardController::new(
+                    context,
+                    KeyboardLayout::KeyboardMouse,
+                    &bindings,
+                    &mouse_pos,
+                ),
+            ),
+            tick_duration: config.tick_duration(),
+            accumulated_time: 0.0,
+            recording: Replay::new(
+                seed,
+                config.clone(),
+                default(),
+                waves.clone(),
+                map.clone(),
+                1,
+            ),
+            playback: None,
+            desynced: false,
+            replay_message: None,
+            net: None,
+            aim_sounds: HashMap::new(),
             quad_geometry: ugli::VertexBuffer::new_static(
                 context.ugli(),
                 vec![
@@ -200,9 +1202,10 @@ impl Game {
                             0.02,
                         ),
                         pos: vec2(
-                            global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
-                            global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
+                            global_rng().gen_range(-config.arena.size..=config.arena.size),
+                            global_rng().gen_range(-config.arena.size..=config.arena.size),
                         ),
+                        prev_pos: vec2(0.0, 0.0),
                         vel: vec2(
                             global_rng().gen_range(-1.0..=1.0),
                             global_rng().gen_range(-1.0..=1.0),
@@ -212,227 +1215,237 @@ impl Game {
                 }
                 ps
             },
+            shockwaves: Vec::new(),
             particle_instances: ugli::VertexBuffer::new_dynamic(context.ugli(), Vec::new()),
             particle_program: context
                 .shader_lib()
                 .compile(include_str!("particle.glsl"))
                 .unwrap(),
-            mouse_pos: Rc::new(Cell::new(vec2(0.0, 0.0))),
-            camera_pos: vec2(0.0, 0.0),
-            next_wave_timer: 0.0,
-            next_wave: 1,
-            start: 0.0,
+            obstacle_geometry: ugli::VertexBuffer::new_dynamic(context.ugli(), Vec::new()),
+            obstacle_program: context
+                .shader_lib()
+                .compile(include_str!("obstacle.glsl"))
+                .unwrap(),
+            config,
+            waves,
+            map,
+            #[cfg(not(target_arch = "wasm32"))]
+            config_watcher: ConfigWatcher::new("config.json"),
+            config_notice,
+            show_mass: false,
+            difficulty: default(),
+            topology: config.arena.topology,
+            inputs: vec![InputDevice::Keyboard(KeyboardLayout::KeyboardMouse)],
+            local_ids: Vec::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            gamepads: Rc::new(RefCell::new(Gamepads::new())),
+            menu: true,
+            bindings,
+            bindings_screen: None,
+            settings,
+            settings_screen: None,
+            pause: None,
+            music: None,
+            mouse_pos,
+            focus: Focus::new(),
+            cameras: vec![vec2(0.0, 0.0)],
             font: geng::Font::new(
                 context,
                 include_bytes!("../font/Simply Rounded Bold.ttf").to_vec(),
             )
             .unwrap(),
-            waves_finished: 0,
-            kills: 0,
-            time_played: 0.0,
         };
-        game.reset();
+        if let Some(replay) = replay {
+            game.menu = false;
+            game.play(Rc::new(replay));
+        }
+        if let Some(connection) = connection {
+            game.menu = false;
+            game.world = World::empty(0, game.config.clone());
+            // Only one player per connection
+            game.net = Some(NetClient::new(connection, game.controller(game.inputs[0])));
+        }
         game
     }
 
-    fn spawn_enemy(&mut self) {
-        loop {
-            let enemy = Player::new(
-                vec2(
-                    global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
-                    global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
-                ),
-                Color::RED,
-                BotController,
-                1,
-            );
-            if Self::delta_pos(self.camera_pos, enemy.pos).len() > Self::CAMERA_FOV * 2.0 {
-                self.players.push(enemy);
-                break;
-            }
+    /// Ids of the players on this machine, each seen in its own view.
+    fn local_ids(&self) -> Vec<usize> {
+        match self.net {
+            Some(ref net) => net.player_id().into_iter().collect(),
+            None => self.local_ids.clone(),
         }
     }
-}
 
-impl geng::State for Game {
-    fn update(&mut self, delta_time: f64) {
-        let mut player_alive = false;
-        for player in &self.players {
-            player.act(self);
-            if player.team_id == 0 {
-                player_alive = true;
-            }
-        }
-        let delta_time = delta_time as f32;
+    fn player(&self, id: usize) -> Option<&Player> {
+        self.world.players.iter().find(|p| p.owner_id == Some(id))
+    }
 
-        for p in &mut self.background_particles {
-            p.update(delta_time);
-        }
+    /// Whether any local player is still in the game.
+    fn local_alive(&self) -> bool {
+        self.local_ids()
+            .into_iter()
+            .any(|id| self.player(id).is_some())
+    }
 
-        for player in &mut self.players {
-            if self.start > Self::START {
-                player.size -= Self::PLAYER_DEATH_SPEED * delta_time;
-            }
-            if let Some(e) = player.update(&self.assets, delta_time) {
-                self.projectiles.push(e);
+    /// Screen areas of `count` views: the whole screen, two halves or four quarters.
+    fn view_rects(count: usize, framebuffer_size: Vec2<f32>) -> Vec<AABB<f32>> {
+        let size = framebuffer_size;
+        match count {
+            0 | 1 => vec![AABB::from_corners(vec2(0.0, 0.0), size)],
+            2 => vec![
+                AABB::from_corners(vec2(0.0, 0.0), vec2(size.x / 2.0, size.y)),
+                AABB::from_corners(vec2(size.x / 2.0, 0.0), size),
+            ],
+            _ => {
+                let half = size / 2.0;
+                [
+                    vec2(0.0, half.y),
+                    vec2(half.x, half.y),
+                    vec2(0.0, 0.0),
+                    vec2(half.x, 0.0),
+                ]
+                .iter()
+                .take(count)
+                .map(|&corner| AABB::from_corners(corner, corner + half))
+                .collect()
             }
-            if player.size <= 0.0 {
-                self.food.push(Food::new(
-                    player.pos,
-                    Player::INITIAL_SIZE / Self::FOOD_K.sqrt(),
-                ));
-                play_sound(&self.assets.death, player.pos);
-                if player.team_id != 0 && player_alive {
-                    self.kills += 1;
-                }
-            }
-        }
-        self.players.retain(|e| e.size > 0.0);
-        for e in &mut self.projectiles {
-            e.size -= Self::PROJECTILE_DEATH_SPEED * delta_time;
-            e.update(delta_time);
         }
-        let assets = &self.assets;
-        self.projectiles.retain(|e| e.alive(assets));
-        for i in 0..self.players.len() {
-            let (head, tail) = self.players.split_at_mut(i);
-            let cur = &mut tail[0];
-            for prev in head {
-                Entity::collide(prev, cur);
-            }
-        }
-        for e in &mut self.projectiles {
-            for player in &mut self.players {
-                if e.owner_id != player.owner_id {
-                    if e.hit(player, Self::PROJECTILE_STRENGTH) {
-                        e.actually_hit = true;
-                    }
-                }
-            }
-        }
-        for i in 0..self.projectiles.len() {
-            let (head, tail) = self.projectiles.split_at_mut(i);
-            let cur = &mut tail[0];
-            for prev in head {
-                cur.hit(prev, 1.0);
-            }
-        }
-        self.next_food -= delta_time;
-        while self.next_food < 0.0 {
-            self.next_food += global_rng().gen_range(Self::FOOD_SPAWN.start..=Self::FOOD_SPAWN.end);
-            if self.food.len() < Self::MAX_FOOD {
-                self.food.push(Food::new(
-                    vec2(
-                        global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
-                        global_rng().gen_range(-Self::WORLD_SIZE..=Self::WORLD_SIZE),
-                    ),
-                    Self::FOOD_SIZE.start
-                        + global_rng().gen_range(0.0f32..=1.0f32).powf(4.0)
-                            * (Self::FOOD_SIZE.end - Self::FOOD_SIZE.start),
-                ));
-            }
-        }
-        for f in &mut self.food {
-            f.update(delta_time);
-        }
-        for f in &mut self.food {
-            for player in &mut self.players {
-                player.consume(f, Self::FOOD_K);
-            }
-            if f.size <= 0.0 {
-                play_sound(&self.assets.heal, f.pos);
-            }
-        }
-        self.food.retain(|e| e.size > 0.0);
+    }
 
-        if self.players.iter().filter(|p| p.team_id != 0).count() == 0 {
-            self.next_wave_timer = self.next_wave_timer.min(Self::WAVE_PAUSE);
+    /// Obstacles and walls, in world coordinates.
+    fn update_obstacle_geometry(&mut self) {
+        let geometry: &mut Vec<_> = &mut self.obstacle_geometry;
+        geometry.clear();
+        for obstacle in &self.world.map.obstacles {
+            let a_color = Self::obstacle_color(obstacle.projectiles);
+            geometry.extend(
+                obstacle
+                    .shape
+                    .triangles()
+                    .into_iter()
+                    .map(|a_pos| ObstacleVertex { a_pos, a_color }),
+            );
         }
-        if self.start > Self::START {
-            self.next_wave_timer -= delta_time;
-            if self.next_wave_timer < 0.0 {
-                self.next_wave_timer = Self::TIME_BETWEEN_WAVES;
-                for _ in 0..self.next_wave {
-                    self.spawn_enemy();
-                }
-                if player_alive {
-                    self.waves_finished = self.next_wave.max(1) - 1;
-                }
-                self.next_wave += 1;
-            }
-            if player_alive {
-                self.time_played += delta_time;
+        let arena = &self.world.config.arena;
+        if !arena.wraps() {
+            let a_color = Self::obstacle_color(arena.wall_projectiles);
+            // A band along each wall, wide enough to fill the view beyond it
+            let inner = arena.size;
+            let outer = arena.size + Self::CAMERA_FOV * 4.0;
+            for &(dir, side) in &[
+                (vec2(1.0, 0.0), vec2(0.0, 1.0)),
+                (vec2(0.0, 1.0), vec2(-1.0, 0.0)),
+                (vec2(-1.0, 0.0), vec2(0.0, -1.0)),
+                (vec2(0.0, -1.0), vec2(1.0, 0.0)),
+            ] {
+                let a = dir * inner - side * outer;
+                let b = dir * inner + side * outer;
+                let c = dir * outer + side * outer;
+                let d = dir * outer - side * outer;
+                geometry.extend(
+                    [a, b, c, a, c, d]
+                        .iter()
+                        .map(|&a_pos| ObstacleVertex { a_pos, a_color }),
+                );
             }
         }
     }
-    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
-        let mut player_alive = false;
-        for player in &self.players {
-            if player.team_id == 0 {
-                player_alive = true;
-                self.camera_pos = player.pos;
-                unsafe {
-                    CAMERA_POS = self.camera_pos;
-                }
-                self.start = self.start.max(player.pos.len());
-            }
-        }
-        let framebuffer_size = framebuffer.size().map(|x| x as f32);
-        ugli::clear(framebuffer, Some(Color::BLACK), None);
 
-        let view_matrix = Mat4::scale(vec3(framebuffer_size.y / framebuffer_size.x, 1.0, 1.0))
+    /// The world as seen from the camera of view `index`, drawn into `view`.
+    fn draw_view(
+        &mut self,
+        framebuffer: &mut ugli::Framebuffer,
+        index: usize,
+        view: AABB<f32>,
+        local_ids: &[usize],
+        alpha: f32,
+        remote_alpha: f32,
+    ) {
+        let camera_pos = self.cameras[index];
+        let view_matrix = Mat4::scale(vec3(view.height() / view.width(), 1.0, 1.0))
             * Mat4::scale_uniform(1.0 / Self::CAMERA_FOV)
-            * Mat4::translate(-self.camera_pos.extend(0.0));
-        self.mouse_pos.set({
-            let mouse_pos = self.context.window().mouse_pos().map(|x| x as f32);
-            let mouse_pos = vec2(
-                mouse_pos.x / framebuffer_size.x * 2.0 - 1.0,
-                mouse_pos.y / framebuffer_size.y * 2.0 - 1.0,
-            );
-            let mouse_pos = view_matrix.inverse() * vec4(mouse_pos.x, mouse_pos.y, 0.0, 1.0);
-            let mouse_pos = vec2(mouse_pos.x, mouse_pos.y);
-            mouse_pos
-        });
+            * Mat4::translate(-camera_pos.extend(0.0));
+        let aiming_with_mouse = self.inputs.get(index)
+            == Some(&InputDevice::Keyboard(KeyboardLayout::KeyboardMouse))
+            && self.playback.is_none()
+            && !self.menu;
+        if aiming_with_mouse {
+            self.mouse_pos.set({
+                let mouse_pos = self.context.window().mouse_pos().map(|x| x as f32);
+                let mouse_pos = vec2(
+                    (mouse_pos.x - view.x_min) / view.width() * 2.0 - 1.0,
+                    (mouse_pos.y - view.y_min) / view.height() * 2.0 - 1.0,
+                );
+                let mouse_pos = view_matrix.inverse() * vec4(mouse_pos.x, mouse_pos.y, 0.0, 1.0);
+                let mouse_pos = vec2(mouse_pos.x, mouse_pos.y);
+                mouse_pos
+            });
+        }
+        let local_pos = local_ids
+            .get(index)
+            .and_then(|&id| self.player(id))
+            .map(|player| player.render_pos(alpha));
         {
             let particles: &mut Vec<_> = &mut self.particle_instances;
             particles.clear();
 
             for p in &self.background_particles {
-                p.draw(particles);
+                p.draw(1.0, particles);
             }
 
-            if player_alive {
-                let dv =
-                    (self.mouse_pos.get() - self.camera_pos).normalize() * Self::CAMERA_FOV * 2.0;
+            if let (Some(pos), true) = (local_pos, aiming_with_mouse) {
+                let dv = (self.mouse_pos.get() - pos).normalize() * Self::CAMERA_FOV * 2.0;
                 const N: usize = 40;
                 for i in 1..=N {
                     particles.push(ParticleInstance {
-                        i_pos: self.camera_pos + dv * i as f32 / N as f32,
+                        i_pos: pos + dv * i as f32 / N as f32,
                         i_color: Color::rgba(0.5, 0.5, 1.0, 0.4),
                         i_size: 0.1,
                     });
                 }
             }
 
-            for f in &self.food {
-                f.draw(particles);
+            for f in &self.world.food {
+                f.draw(alpha, particles);
             }
-            for player in &self.players {
-                player.draw(particles);
+            for player in &self.world.players {
+                if local_ids.contains(&player.owner_id.unwrap()) {
+                    player.draw(alpha, particles);
+                } else {
+                    player.draw(remote_alpha, particles);
+                }
             }
-            for e in &self.projectiles {
-                e.draw(particles);
+            for e in &self.world.projectiles {
+                e.draw(remote_alpha, particles);
             }
-            for player in &self.players {
+            for shockwave in &self.shockwaves {
+                const N: usize = 60;
+                let radius = shockwave.time * Self::SHOCKWAVE_SPEED;
+                let mut color = Archetype::Boss.color();
+                color.a = 1.0 - shockwave.time / Self::SHOCKWAVE_TIME;
+                for i in 0..N {
+                    let angle = 2.0 * std::f32::consts::PI * i as f32 / N as f32;
+                    particles.push(ParticleInstance {
+                        i_pos: shockwave.pos + vec2(angle.cos(), angle.sin()) * radius,
+                        i_color: color,
+                        i_size: 0.5,
+                    });
+                }
+            }
+            for player in &self.world.players {
                 if player.team_id != 0 {
-                    let dv = Self::delta_pos(self.camera_pos, player.pos);
+                    let dv = self
+                        .world
+                        .config
+                        .arena
+                        .delta_pos(camera_pos, player.render_pos(remote_alpha));
                     let max_y = Self::CAMERA_FOV;
-                    let max_x = max_y * framebuffer_size.x / framebuffer_size.y;
+                    let max_x = max_y * view.width() / view.height();
                     if dv.x.abs() > max_x || dv.y.abs() > max_y {
                         let mut color = player.color;
                         color.a = 0.5;
                         particles.push(ParticleInstance {
-                            i_pos: self.camera_pos
+                            i_pos: camera_pos
                                 + vec2(clamp_abs(dv.x, max_x), clamp_abs(dv.y, max_y)),
                             i_color: color,
                             i_size: player.size,
@@ -441,8 +1454,36 @@ impl geng::State for Game {
                 }
             }
         }
-        for i in -1..=1 {
-            for j in -1..=1 {
+        let palette = self.settings.palette;
+        for p in self.particle_instances.iter_mut() {
+            p.i_color = palette.apply(p.i_color);
+        }
+        // Everything is drawn again around the world, so that its edges are seamless
+        let world_size = self.world.config.arena.size;
+        let images = if self.world.config.arena.wraps() {
+            -1..=1
+        } else {
+            0..=0
+        };
+        let viewport = Some(view.map(|x| x as usize));
+        for i in images.clone() {
+            for j in images.clone() {
+                let world_offset = vec2(i as f32 * world_size, j as f32 * world_size) * 2.0;
+                ugli::draw(
+                    framebuffer,
+                    &self.obstacle_program,
+                    ugli::DrawMode::Triangles,
+                    &self.obstacle_geometry,
+                    ugli::uniforms! {
+                        u_view_matrix: view_matrix,
+                        u_world_offset: world_offset,
+                    },
+                    ugli::DrawParameters {
+                        blend_mode: Some(default()),
+                        viewport,
+                        ..default()
+                    },
+                );
                 ugli::draw(
                     framebuffer,
                     &self.particle_program,
@@ -450,17 +1491,230 @@ impl geng::State for Game {
                     ugli::instanced(&self.quad_geometry, &self.particle_instances),
                     ugli::uniforms! {
                         u_view_matrix: view_matrix,
-                        u_world_offset: vec2(i as f32 * Self::WORLD_SIZE, j as f32 * Self::WORLD_SIZE) * 2.0,
+                        u_world_offset: world_offset,
                     },
                     ugli::DrawParameters {
                         blend_mode: Some(default()),
+                        viewport,
                         ..default()
                     },
                 );
             }
         }
+    }
+
+    fn update_aim_sounds(&mut self) {
+        let mut aiming = HashSet::new();
+        for player in &self.world.players {
+            if player.projectile.is_some() {
+                let id = player.owner_id.unwrap();
+                aiming.insert(id);
+                let arena = &self.world.config.arena;
+                let volume = self.settings.sfx_volume;
+                if let Some(sound) = self.aim_sounds.get(&id) {
+                    sound.set_pos(player.pos, arena, &self.cameras, volume);
+                } else {
+                    self.aim_sounds.insert(
+                        id,
+                        play_sound(&self.assets.aim, player.pos, arena, &self.cameras, volume),
+                    );
+                }
+            }
+        }
+        self.aim_sounds.retain(|id, sound| {
+            if aiming.contains(id) {
+                true
+            } else {
+                sound.stop();
+                false
+            }
+        });
+    }
+}
+
+impl geng::State for Game {
+    fn update(&mut self, delta_time: f64) {
+        let delta_time = delta_time as f32;
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.gamepads.borrow_mut().update();
+
+        for p in &mut self.background_particles {
+            p.update(delta_time, &self.world.config.arena);
+            if let Some(contact) = self.world.config.arena.wall_contact(p.pos, 0.0) {
+                p.pos += contact.normal * contact.depth;
+                let along = Vec2::dot(p.vel, contact.normal);
+                if along < 0.0 {
+                    p.vel -= contact.normal * along * 2.0;
+                }
+            }
+        }
+
+        #[cfg(not(target_arch = "wasm32"))]
+        self.reload_config(delta_time);
+        if let Some(ref mut notice) = self.config_notice {
+            notice.time_left -= delta_time;
+            if notice.time_left < 0.0 {
+                self.config_notice = None;
+            }
+        }
+
+        // A frame this late means the window was hidden, even where focus can not be told
+        if delta_time > Self::HIDDEN_FRAME_TIME || !self.focus.has_focus() {
+            self.auto_pause();
+        }
+
+        if self.menu || self.paused() {
+            return;
+        }
+
+        let mut events = Vec::new();
+        if let Some(ref mut net) = self.net {
+            events.extend(net.update(&mut self.world, delta_time));
+            self.tick_duration = net.tick_duration();
+        }
+
+        self.accumulated_time += delta_time.min(Self::MAX_FRAME_TIME);
+        while self.accumulated_time >= self.tick_duration {
+            self.accumulated_time -= self.tick_duration;
+            if let Some(ref mut net) = self.net {
+                net.tick(&mut self.world);
+                continue;
+            }
+            let tick = self.world.tick;
+            if let Some(config) = self
+                .playback
+                .as_ref()
+                .and_then(|replay| replay.config_change(tick))
+            {
+                self.tick_duration = config.tick_duration();
+                self.world.config = config.clone();
+            }
+            let actions = self.world.act();
+            match self.playback {
+                Some(ref replay) => {
+                    let tick = self.world.tick;
+                    if !self.desynced
+                        && tick < replay.tick_count()
+                        && self
+                            .world
+                            .players
+                            .iter()
+                            .zip(&actions)
+                            .any(|(player, &action)| {
+                                replay.action(tick, player.owner_id.unwrap()) != Some(action)
+                            })
+                    {
+                        warn!("Replay desynced at tick {}", tick);
+                        self.desynced = true;
+                    }
+                }
+                None => self.recording.record(&self.world.players, &actions),
+            }
+            self.world.step(self.tick_duration, &actions);
+        }
+        events.extend(self.world.take_events());
+        let arena = &self.world.config.arena;
+        let listeners = &self.cameras;
+        let volume = self.settings.sfx_volume;
+        let assets = &self.assets;
+        let play = |sound: &geng::Sound, pos| play_sound(sound, pos, arena, listeners, volume);
+        for event in events {
+            match event {
+                Event::Shoot { pos } => play(&assets.shoot, pos),
+                Event::Hit { pos } => play(&assets.hit, pos),
+                Event::Death { pos } => play(&assets.death, pos),
+                Event::Heal { pos } => play(&assets.heal, pos),
+                // The sting is heard wherever the boss is
+                Event::BossSpawn { .. } => play(&assets.boss, listeners[0]),
+                Event::BossDeath { pos } => {
+                    self.shockwaves.push(Shockwave { pos, time: 0.0 });
+                    play(&assets.death, pos);
+                    play(&assets.boss, listeners[0])
+                }
+            };
+        }
+        for shockwave in &mut self.shockwaves {
+            shockwave.time += delta_time;
+        }
+        self.shockwaves
+            .retain(|shockwave| shockwave.time < Self::SHOCKWAVE_TIME);
+        self.update_aim_sounds();
+    }
+    fn draw(&mut self, framebuffer: &mut ugli::Framebuffer) {
+        let alpha = self.accumulated_time / self.tick_duration;
+        let remote_alpha = match self.net {
+            Some(ref net) => net.snapshot_alpha(),
+            None => alpha,
+        };
+        let local_ids = self.local_ids();
+        let player_alive = self.local_alive();
+        self.cameras.resize(local_ids.len().max(1), vec2(0.0, 0.0));
+        for (index, &id) in local_ids.iter().enumerate() {
+            // Players out of the game watch their teammates
+            let pos = self
+                .player(id)
+                .or_else(|| local_ids.iter().find_map(|&id| self.player(id)))
+                .map(|player| player.render_pos(alpha));
+            if let Some(pos) = pos {
+                // In a walled arena, keep most of the view inside
+                self.cameras[index] = self.world.config.arena.confine(pos, Self::CAMERA_FOV / 2.0);
+            }
+        }
+        let framebuffer_size = framebuffer.size().map(|x| x as f32);
+        ugli::clear(framebuffer, Some(Color::BLACK), None);
+
+        self.update_obstacle_geometry();
+        let views = Self::view_rects(self.cameras.len(), framebuffer_size);
+        for (index, &view) in views.iter().enumerate() {
+            self.draw_view(framebuffer, index, view, &local_ids, alpha, remote_alpha);
+        }
+        if views.len() > 1 {
+            let draw_2d = self.context.draw_2d();
+            let color = Color::rgba(0.5, 0.5, 0.5, 1.0);
+            draw_2d.quad(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                AABB::from_corners(
+                    vec2(framebuffer_size.x / 2.0 - 1.0, 0.0),
+                    vec2(framebuffer_size.x / 2.0 + 1.0, framebuffer_size.y),
+                ),
+                color,
+            );
+            if views.len() > 2 {
+                draw_2d.quad(
+                    framebuffer,
+                    &geng::PixelPerfectCamera,
+                    AABB::from_corners(
+                        vec2(0.0, framebuffer_size.y / 2.0 - 1.0),
+                        vec2(framebuffer_size.x, framebuffer_size.y / 2.0 + 1.0),
+                    ),
+                    color,
+                );
+            }
+        }
 
-        if !player_alive {
+        let bindings = self.bindings.borrow();
+        let overlay = self.bindings_screen.is_some()
+            || self.settings_screen.is_some()
+            || self.pause.is_some();
+        if overlay && !self.menu {
+            self.context.draw_2d().quad(
+                framebuffer,
+                &geng::PixelPerfectCamera,
+                AABB::from_corners(vec2(0.0, 0.0), framebuffer_size),
+                Color::rgba(0.0, 0.0, 0.0, 0.6),
+            );
+        }
+        if let Some(ref screen) = self.bindings_screen {
+            self.draw_bindings_screen(framebuffer, screen);
+        } else if let Some(selected) = self.settings_screen {
+            self.draw_settings_screen(framebuffer, selected);
+        } else if let Some(ref menu) = self.pause {
+            self.draw_pause_menu(framebuffer, menu);
+        } else if self.menu {
+            self.draw_menu(framebuffer);
+        } else if !player_alive {
             let font = &self.font;
             let scale = framebuffer_size.y / 20.0;
             let mid = framebuffer_size / 2.0;
@@ -474,7 +1728,7 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                &format!("time played: {} secs", self.time_played as i64),
+                &format!("time played: {} secs", self.world.time_played as i64),
                 vec2(0.0, 3.0 * scale) + mid,
                 0.5,
                 scale,
@@ -482,7 +1736,7 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                &format!("waves finished: {}", self.waves_finished),
+                &format!("waves finished: {}", self.world.waves_finished),
                 vec2(0.0, 2.0 * scale) + mid,
                 0.5,
                 scale,
@@ -490,7 +1744,7 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                &format!("enemies killed: {}", self.kills),
+                &format!("enemies killed: {}", self.world.kills),
                 vec2(0.0, 1.0 * scale) + mid,
                 0.5,
                 scale,
@@ -498,20 +1752,52 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                "press R to restart",
+                &format!("press {} to restart", bindings.restart.name()),
                 vec2(0.0, -4.0 * scale) + mid,
                 0.5,
                 scale * 2.0,
                 Color::rgba(0.5, 0.5, 0.5, 1.0),
             );
-        } else if self.start < Self::START {
-            let alpha = 1.0 - self.start / Self::START;
+            if let Some(ref message) = self.replay_message {
+                font.draw_aligned(
+                    framebuffer,
+                    message,
+                    vec2(0.0, -6.0 * scale) + mid,
+                    0.5,
+                    scale,
+                    Color::rgba(0.5, 0.5, 0.5, 1.0),
+                );
+            } else if cfg!(not(target_arch = "wasm32"))
+                && self.playback.is_none()
+                && self.net.is_none()
+            {
+                font.draw_aligned(
+                    framebuffer,
+                    &format!("press {} to save replay", bindings.save_replay.name()),
+                    vec2(0.0, -6.0 * scale) + mid,
+                    0.5,
+                    scale,
+                    Color::rgba(0.5, 0.5, 0.5, 1.0),
+                );
+            }
+            if self.playback.is_none() && self.net.is_none() {
+                font.draw_aligned(
+                    framebuffer,
+                    &format!("press {} to change difficulty", bindings.menu.name()),
+                    vec2(0.0, -7.0 * scale) + mid,
+                    0.5,
+                    scale,
+                    Color::rgba(0.5, 0.5, 0.5, 1.0),
+                );
+            }
+        } else if self.world.start < self.world.config.start_distance {
+            let alpha = 1.0 - self.world.start / self.world.config.start_distance;
             let font = &self.font;
             let scale = framebuffer_size.y / 20.0;
             let mid = framebuffer_size / 2.0;
             font.draw_aligned(
                 framebuffer,
-                "WASD to move",
+                &format!("{} to move", bindings.movement_name()),
                 vec2(0.0, 5.0 * scale) + mid,
                 0.5,
                 scale * 2.0,
@@ -519,7 +1805,7 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                "LMB to shoot",
+                &format!("{} to shoot", bindings.shoot.name()),
                 vec2(0.0, 3.0 * scale) + mid,
                 0.5,
                 scale * 2.0,
@@ -527,12 +1813,28 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                "F to toggle fullscreen",
+                &format!("{} to toggle fullscreen", bindings.fullscreen.name()),
                 vec2(0.0, 2.0 * scale) + mid,
                 0.5,
                 scale,
                 Color::rgba(0.5, 0.5, 0.5, alpha),
             );
+            font.draw_aligned(
+                framebuffer,
+                "1-5 to switch weapons",
+                vec2(0.0, 1.0 * scale) + mid,
+                0.5,
+                scale,
+                Color::rgba(0.5, 0.5, 0.5, alpha),
+            );
+            font.draw_aligned(
+                framebuffer,
+                &format!("{} to pause", bindings.pause.name()),
+                vec2(0.0, 0.0) + mid,
+                0.5,
+                scale,
+                Color::rgba(0.5, 0.5, 0.5, alpha),
+            );
             font.draw_aligned(
                 framebuffer,
                 "Survive as long as possible",
@@ -548,9 +1850,15 @@ impl geng::State for Game {
             font.draw_aligned(
                 framebuffer,
                 &format!(
-                    "wave #{} in {} secs",
-                    self.next_wave,
-                    f32::floor(self.next_wave_timer),
+                    "{}wave #{} ({}) in {} secs",
+                    if self.world.waves.is_boss_wave(self.world.next_wave) {
+                        "boss "
+                    } else {
+                        ""
+                    },
+                    self.world.next_wave,
+                    self.world.wave_difficulty(self.world.next_wave).name(),
+                    f32::floor(self.world.next_wave_timer),
                 ),
                 vec2(0.0, 8.0 * scale) + mid,
                 0.5,
@@ -559,41 +1867,148 @@ impl geng::State for Game {
             );
             font.draw_aligned(
                 framebuffer,
-                &format!(
-                    "{} enemies",
-                    self.players.iter().filter(|p| p.team_id != 0).count(),
-                ),
+                &format!("{} enemies", self.world.enemy_count()),
                 vec2(0.0, -9.0 * scale) + mid,
                 0.5,
                 scale,
                 Color::rgba(1.0, 1.0, 1.0, 0.5),
             );
+            self.draw_boss_bar(framebuffer);
+            for (&id, &view) in local_ids.iter().zip(&views) {
+                match self.player(id) {
+                    Some(player) => {
+                        self.draw_status_effects(framebuffer, view, player);
+                        self.draw_weapon(framebuffer, view, player);
+                    }
+                    None => font.draw_aligned(
+                        framebuffer,
+                        "DEAD",
+                        view.center() + vec2(0.0, scale * 2.0),
+                        0.5,
+                        scale,
+                        Color::rgba(0.5, 0.5, 0.5, 1.0),
+                    ),
+                }
+            }
+        }
+
+        if let Some(ref notice) = self.config_notice {
+            let scale = framebuffer_size.y / 20.0;
+            let mut color = notice.color;
+            color.a = notice.time_left.min(1.0);
+            self.font.draw_aligned(
+                framebuffer,
+                &notice.text,
+                vec2(framebuffer_size.x / 2.0, framebuffer_size.y - scale),
+                0.5,
+                scale * 0.5,
+                color,
+            );
+        }
+
+        if self.show_mass && self.net.is_none() {
+            self.draw_mass_overlay(framebuffer);
         }
     }
     fn handle_event(&mut self, event: geng::Event) {
         if let geng::Event::KeyDown { .. } | geng::Event::MouseDown { .. } = event {
-            check_music_start(&self.assets);
+            self.start_music();
+        }
+        if self.bindings_screen.is_some() {
+            self.handle_bindings_event(event);
+            return;
         }
-        match event {
-            geng::Event::KeyDown { key } => match key {
-                geng::Key::R => self.reset(),
-                geng::Key::F => self.context.window().toggle_fullscreen(),
+        if self.settings_screen.is_some() {
+            self.handle_settings_event(event);
+            return;
+        }
+        if self.pause.is_some() {
+            self.handle_pause_event(event);
+            return;
+        }
+        if self.menu {
+            self.handle_menu_event(event);
+            return;
+        }
+        let controls: Vec<_> = match Input::pressed_by(&event) {
+            Some(input) => self.bindings.borrow().controls(input).collect(),
+            None => return,
+        };
+        let local_match_over =
+            !self.world.player_alive() && self.playback.is_none() && self.net.is_none();
+        for control in controls {
+            match control {
+                // A run in progress is not thrown away by a stray key
+                Control::Restart if self.local_alive() && self.net.is_none() => {
+                    self.open_pause();
+                    let menu = self.pause.as_mut().unwrap();
+                    menu.selected = PauseItem::ALL
+                        .iter()
+                        .position(|&item| item == PauseItem::Restart)
+                        .unwrap();
+                    menu.confirm_restart = true;
+                }
+                Control::Restart => self.restart(),
+                Control::Fullscreen => self.context.window().toggle_fullscreen(),
+                Control::Pause => self.open_pause(),
+                Control::MassOverlay => self.show_mass = !self.show_mass,
+                Control::Menu if local_match_over => self.menu = true,
+                #[cfg(not(target_arch = "wasm32"))]
+                Control::SaveReplay if local_match_over => self.save_replay(),
                 _ => {}
-            },
-            _ => {}
+            }
         }
     }
 }
 
-fn check_music_start(assets: &Assets) {
-    static STARTED: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
-    if !STARTED.fetch_or(true, std::sync::atomic::Ordering::Relaxed) {
-        let mut music = assets.music.play();
-        music.set_volume(0.2);
+fn mass_balance_lines(name: &str, b: &MassBalance) -> Vec<String> {
+    vec![
+        format!("{} +{:.3} -{:.3}", name, b.created(), b.destroyed()),
+        format!(
+            "  + spawned {:.3}, food {:.3}, dropped {:.3}, eating {:.3}, fed {:.3}, charge {:.3}",
+            b.spawned, b.food_spawned, b.dropped, b.eating_gain, b.fed_gain, b.charge_gain,
+        ),
+        format!(
+            "  - eaten {:.3}, fed {:.3}, decay {:.3}, charge {:.3}, shot decay {:.3}, hits {:.3}/{:.3}, absorbed {:.3}, removed {:.3}",
+            b.eaten, b.fed, b.decay, b.charge_cost, b.projectile_decay, b.hit_spent, b.hit_damage, b.absorbed, b.removed,
+        ),
+    ]
+}
+
+#[derive(Default)]
+struct Opt {
+    replay: Option<String>,
+    connect: Option<String>,
+}
+
+impl Opt {
+    fn parse() -> Self {
+        let mut opt = Self::default();
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--replay" => {
+                    opt.replay = Some(args.next().expect("Expected a replay file after --replay"))
+                }
+                "--connect" => {
+                    opt.connect = Some(
+                        args.next()
+                            .expect("Expected a server address after --connect"),
+                    )
+                }
+                _ => panic!("Unexpected argument {:?}", arg),
+            }
+        }
+        opt
     }
 }
 
 fn main() {
+    let opt = Opt::parse();
+    let replay = opt.replay.map(|path| {
+        let file = std::fs::File::open(&path).expect("Failed to open replay file");
+        Replay::read_from(std::io::BufReader::new(file)).expect("Failed to load replay")
+    });
     if let Ok(dir) = std::env::var("CARGO_MANIFEST_DIR") {
         std::env::set_current_dir(std::path::Path::new(&dir).join("static")).unwrap();
     }
@@ -604,13 +2019,24 @@ fn main() {
         ..default()
     }));
     let geng_clone = geng.clone();
+    let assets = <Assets as geng::LoadAsset>::load(&geng, ".");
+    let connection = opt
+        .connect
+        .map(|addr| geng::net::client::connect::<ServerMessage, ClientMessage>(&addr));
     geng::run(
         geng.clone(),
         geng::LoadingScreen::new(
             &geng,
             geng::EmptyLoadingScreen,
-            geng::LoadAsset::load(&geng, "."),
-            move |assets| Game::new(&geng_clone, assets.unwrap()),
+            async move {
+                let assets = assets.await;
+                let connection = match connection {
+                    Some(connection) => Some(connection.await),
+                    None => None,
+                };
+                (assets, connection)
+            },
+            move |(assets, connection)| Game::new(&geng_clone, assets.unwrap(), replay, connection),
         ),
     );
 }
@@ -0,0 +1,275 @@
+use crate::*;
+
+/// Static terrain, loaded from `map.json`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Map {
+    pub obstacles: Vec<Obstacle>,
+}
+
+/// Blocks players and food, and either bounces or absorbs projectiles.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Obstacle {
+    pub shape: Shape,
+    #[serde(default)]
+    pub projectiles: ProjectileResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum Shape {
+    Circle {
+        center: Vec2<f32>,
+        radius: f32,
+    },
+    /// Corners of a convex polygon, counter-clockwise.
+    Polygon {
+        points: Vec<Vec2<f32>>,
+    },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ProjectileResponse {
+    Bounce,
+    /// The projectile is lost, mass and all.
+    Absorb,
+}
+
+impl Default for ProjectileResponse {
+    fn default() -> Self {
+        ProjectileResponse::Bounce
+    }
+}
+
+/// How an entity touches an obstacle.
+#[derive(Debug, Copy, Clone)]
+pub struct Contact {
+    /// Out of the obstacle.
+    pub normal: Vec2<f32>,
+    pub depth: f32,
+}
+
+impl Map {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let map: Self = serde_json::from_str(json)?;
+        map.validate()?;
+        Ok(map)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Invalid map in {:?}", path))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, obstacle) in self.obstacles.iter().enumerate() {
+            let name = format!("obstacles[{}]", i);
+            match obstacle.shape {
+                Shape::Circle { radius, .. } => {
+                    check_positive(&format!("{}.radius", name), radius)?;
+                }
+                Shape::Polygon { ref points } => {
+                    if points.len() < 3 {
+                        anyhow::bail!("{}.points must have at least 3 corners", name);
+                    }
+                    for j in 0..points.len() {
+                        let a = points[j];
+                        let b = points[(j + 1) % points.len()];
+                        let c = points[(j + 2) % points.len()];
+                        if Vec2::skew(b - a, c - b) <= 0.0 {
+                            anyhow::bail!(
+                                "{}.points must make a convex polygon, counter-clockwise",
+                                name,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Deepest contact of a circle at `pos` with any obstacle or the arena's walls,
+    /// and what it does to projectiles.
+    pub fn contact(
+        &self,
+        pos: Vec2<f32>,
+        radius: f32,
+        arena: &ArenaConfig,
+    ) -> Option<(Contact, ProjectileResponse)> {
+        let walls = arena
+            .wall_contact(pos, radius)
+            .map(|contact| (contact, arena.wall_projectiles));
+        self.obstacles
+            .iter()
+            .filter_map(|obstacle| {
+                let contact = obstacle.shape.contact(pos, radius, arena)?;
+                Some((contact, obstacle.projectiles))
+            })
+            .chain(walls)
+            .max_by(|(a, _), (b, _)| a.depth.partial_cmp(&b.depth).unwrap())
+    }
+
+    /// Whether the straight way from `a` to `b` goes through no obstacle.
+    pub fn line_of_sight(&self, a: Vec2<f32>, b: Vec2<f32>, arena: &ArenaConfig) -> bool {
+        self.obstacles
+            .iter()
+            .all(|obstacle| !obstacle.shape.blocks(a, b, arena))
+    }
+}
+
+impl Shape {
+    fn center(&self) -> Vec2<f32> {
+        match *self {
+            Shape::Circle { center, .. } => center,
+            Shape::Polygon { ref points } => {
+                points.iter().fold(vec2(0.0, 0.0), |sum, &p| sum + p) / points.len() as f32
+            }
+        }
+    }
+
+    /// Outward normals of the polygon's edges, with a point on each.
+    fn edges(points: &[Vec2<f32>]) -> impl Iterator<Item = (Vec2<f32>, Vec2<f32>)> + '_ {
+        (0..points.len()).map(move |i| {
+            let a = points[i];
+            let b = points[(i + 1) % points.len()];
+            let edge = b - a;
+            (a, vec2(edge.y, -edge.x).normalize())
+        })
+    }
+
+    /// Copy of `pos` wrapped around the world to be the closest to the shape.
+    fn local_pos(&self, pos: Vec2<f32>, arena: &ArenaConfig) -> Vec2<f32> {
+        let center = self.center();
+        center + arena.delta_pos(center, pos)
+    }
+
+    pub fn contact(&self, pos: Vec2<f32>, radius: f32, arena: &ArenaConfig) -> Option<Contact> {
+        let pos = self.local_pos(pos, arena);
+        let (normal, distance) = match *self {
+            Shape::Circle {
+                center,
+                radius: shape_radius,
+            } => {
+                let delta = pos - center;
+                let len = delta.len();
+                let normal = if len > 1e-5 {
+                    delta / len
+                } else {
+                    vec2(1.0, 0.0)
+                };
+                (normal, len - shape_radius)
+            }
+            Shape::Polygon { ref points } => {
+                let (a, normal) = Self::edges(points)
+                    .max_by(|&(a, n), &(b, m)| {
+                        Vec2::dot(pos - a, n)
+                            .partial_cmp(&Vec2::dot(pos - b, m))
+                            .unwrap()
+                    })
+                    .unwrap();
+                let distance = Vec2::dot(pos - a, normal);
+                if distance <= 0.0 {
+                    // Inside, pushed out through the closest edge
+                    (normal, distance)
+                } else {
+                    let closest = (0..points.len())
+                        .map(|i| {
+                            let a = points[i];
+                            let b = points[(i + 1) % points.len()];
+                            let edge = b - a;
+                            let t =
+                                clamp(Vec2::dot(pos - a, edge) / Vec2::dot(edge, edge), 0.0..=1.0);
+                            a + edge * t
+                        })
+                        .min_by(|a, b| (pos - *a).len().partial_cmp(&(pos - *b).len()).unwrap())
+                        .unwrap();
+                    let delta = pos - closest;
+                    let len = delta.len();
+                    if len > 1e-5 {
+                        (delta / len, len)
+                    } else {
+                        (normal, 0.0)
+                    }
+                }
+            }
+        };
+        if distance < radius {
+            Some(Contact {
+                normal,
+                depth: radius - distance,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Whether the segment from `a` to `b` crosses the shape.
+    pub fn blocks(&self, a: Vec2<f32>, b: Vec2<f32>, arena: &ArenaConfig) -> bool {
+        let a = self.local_pos(a, arena);
+        let d = arena.delta_pos(a, b);
+        match *self {
+            Shape::Circle { center, radius } => {
+                let len_sqr = Vec2::dot(d, d);
+                let t = if len_sqr > 1e-5 {
+                    clamp(Vec2::dot(center - a, d) / len_sqr, 0.0..=1.0)
+                } else {
+                    0.0
+                };
+                (a + d * t - center).len() < radius
+            }
+            Shape::Polygon { ref points } => {
+                // Clip the segment by every edge's half-plane
+                let mut enter = 0.0f32;
+                let mut exit = 1.0f32;
+                for (p, normal) in Self::edges(points) {
+                    let start = Vec2::dot(a - p, normal);
+                    let along = Vec2::dot(d, normal);
+                    if along.abs() < 1e-5 {
+                        if start > 0.0 {
+                            return false;
+                        }
+                        continue;
+                    }
+                    let t = -start / along;
+                    if along < 0.0 {
+                        enter = enter.max(t);
+                    } else {
+                        exit = exit.min(t);
+                    }
+                    if enter > exit {
+                        return false;
+                    }
+                }
+                true
+            }
+        }
+    }
+
+    /// Triangles covering the shape, for rendering.
+    pub fn triangles(&self) -> Vec<Vec2<f32>> {
+        const CIRCLE_SEGMENTS: usize = 32;
+        let (center, outline) = match *self {
+            Shape::Circle { center, radius } => (
+                center,
+                (0..CIRCLE_SEGMENTS)
+                    .map(|i| {
+                        let angle = 2.0 * std::f32::consts::PI * i as f32 / CIRCLE_SEGMENTS as f32;
+                        center + vec2(angle.cos(), angle.sin()) * radius
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Shape::Polygon { ref points } => (self.center(), points.clone()),
+        };
+        let mut result = Vec::with_capacity(outline.len() * 3);
+        for i in 0..outline.len() {
+            result.push(center);
+            result.push(outline[i]);
+            result.push(outline[(i + 1) % outline.len()]);
+        }
+        result
+    }
+}
@@ -0,0 +1,89 @@
+use crate::*;
+
+/// Mass moved around by one kind of interaction.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MassBalance {
+    /// Players entering the world.
+    pub spawned: f32,
+    pub food_spawned: f32,
+    /// Food left by dead players.
+    pub dropped: f32,
+    /// Mass of food eaten, and what eaters gained from it.
+    pub eaten: f32,
+    pub eating_gain: f32,
+    /// Given away by healers, and what their allies got.
+    pub fed: f32,
+    pub fed_gain: f32,
+    /// Life lost over time once the game has started, and to poison.
+    pub decay: f32,
+    /// Taken from players charging a shot, and put into the projectile.
+    pub charge_cost: f32,
+    pub charge_gain: f32,
+    pub projectile_decay: f32,
+    /// Lost by projectiles hitting something, and by what they hit.
+    pub hit_spent: f32,
+    pub hit_damage: f32,
+    /// Projectiles lost to obstacles.
+    pub absorbed: f32,
+    /// Players that left, and shots still charging when their owner died.
+    pub removed: f32,
+}
+
+impl MassBalance {
+    pub fn created(&self) -> f32 {
+        self.spawned
+            + self.food_spawned
+            + self.dropped
+            + self.eating_gain
+            + self.fed_gain
+            + self.charge_gain
+    }
+
+    pub fn destroyed(&self) -> f32 {
+        self.eaten
+            + self.fed
+            + self.decay
+            + self.charge_cost
+            + self.projectile_decay
+            + self.hit_spent
+            + self.hit_damage
+            + self.absorbed
+            + self.removed
+    }
+
+    pub fn net(&self) -> f32 {
+        self.created() - self.destroyed()
+    }
+}
+
+/// Accounts for every change of mass in the world, as a whole and per player.
+#[derive(Debug, Clone, Default)]
+pub struct MassLedger {
+    /// Changes during the last tick.
+    pub tick: MassBalance,
+    /// Changes since the start of the match.
+    pub total: MassBalance,
+    /// Changes since the start of the match, by the player responsible for them.
+    pub players: HashMap<usize, MassBalance>,
+}
+
+impl MassLedger {
+    pub fn start_tick(&mut self) {
+        self.tick = default();
+    }
+
+    /// Applies `change` to the tick, the match and, if given, the player's balance.
+    pub fn record(&mut self, owner_id: Option<usize>, change: impl Fn(&mut MassBalance)) {
+        change(&mut self.tick);
+        change(&mut self.total);
+        if let Some(id) = owner_id {
+            change(self.players.entry(id).or_default());
+        }
+    }
+
+    /// How far the actual mass in the world is from what the ledger accounts for.
+    /// Anything beyond float rounding means some change went unrecorded.
+    pub fn drift(&self, world_mass: f32) -> f32 {
+        world_mass - self.total.net()
+    }
+}
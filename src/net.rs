@@ -0,0 +1,216 @@
+use crate::*;
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ClientMessage {
+    Input { id: u64, action: Action },
+    Respawn,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ServerMessage {
+    /// Sent once on connection, the server's balance overrides the local one.
+    Config(Config),
+    Waves(WaveScript),
+    Map(Map),
+    Snapshot(Snapshot),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerState {
+    pub entity: Entity,
+    pub team_id: usize,
+    pub archetype: Option<Archetype>,
+    pub action: Action,
+    pub projectile: Option<Entity>,
+    pub weapon: Weapon,
+    pub status_effects: Vec<StatusEffect>,
+}
+
+/// Authoritative world state, as seen by one particular client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub tick: usize,
+    /// The player controlled by the receiving client, if alive.
+    pub player_id: Option<usize>,
+    /// Last input of the receiving client that was applied.
+    pub last_input: u64,
+    /// Events since the previous snapshot.
+    pub events: Vec<Event>,
+    pub players: Vec<PlayerState>,
+    pub projectiles: Vec<(Entity, Weapon)>,
+    pub food: Vec<(Entity, FoodKind)>,
+    pub next_wave_timer: f32,
+    pub next_wave: usize,
+    pub time_played: f32,
+    pub kills: usize,
+    pub waves_finished: usize,
+    pub start: f32,
+}
+
+impl Snapshot {
+    /// Snapshots are sent every this many ticks.
+    pub const INTERVAL: usize = 3;
+}
+
+/// Controls a player with actions set from elsewhere, e.g. received over the network.
+pub struct RemoteController {
+    action: Rc<Cell<Action>>,
+}
+
+impl RemoteController {
+    pub fn new(action: &Rc<Cell<Action>>) -> Self {
+        Self {
+            action: action.clone(),
+        }
+    }
+}
+
+impl Controller for RemoteController {
+    fn act(&mut self, _: usize, _: &World) -> Action {
+        self.action.get()
+    }
+}
+
+pub type Connection = geng::net::client::Connection<ServerMessage, ClientMessage>;
+
+/// Client side of a networked match.
+///
+/// Mirrors the server's world from snapshots, predicting the local player
+/// by replaying the inputs the server has not acknowledged yet.
+pub struct NetClient {
+    connection: Connection,
+    controller: Box<dyn Controller>,
+    player_id: Option<usize>,
+    tick_duration: f32,
+    next_input: u64,
+    pending_inputs: VecDeque<(u64, Action)>,
+    since_snapshot: f32,
+    rng: StdRng,
+}
+
+impl NetClient {
+    pub fn new<T: Controller + 'static>(connection: Connection, controller: T) -> Self {
+        Self {
+            connection,
+            controller: Box::new(controller),
+            player_id: None,
+            tick_duration: Config::default().tick_duration(),
+            next_input: 0,
+            pending_inputs: VecDeque::new(),
+            since_snapshot: 0.0,
+            rng: StdRng::from_entropy(),
+        }
+    }
+
+    pub fn player_id(&self) -> Option<usize> {
+        self.player_id
+    }
+
+    pub fn tick_duration(&self) -> f32 {
+        self.tick_duration
+    }
+
+    /// How far between the two latest snapshots remote objects should be rendered.
+    pub fn snapshot_alpha(&self) -> f32 {
+        clamp(
+            self.since_snapshot / (Snapshot::INTERVAL as f32 * self.tick_duration),
+            0.0..=1.0,
+        )
+    }
+
+    pub fn respawn(&mut self) {
+        self.pending_inputs.clear();
+        self.connection.send(ClientMessage::Respawn);
+    }
+
+    /// Applies received snapshots, returning the events they carried.
+    pub fn update(&mut self, world: &mut World, delta_time: f32) -> Vec<Event> {
+        self.since_snapshot += delta_time;
+        let mut events = Vec::new();
+        while let Some(message) = self.connection.try_recv() {
+            match message {
+                ServerMessage::Config(config) => {
+                    self.tick_duration = config.tick_duration();
+                    world.config = config;
+                }
+                ServerMessage::Waves(waves) => world.waves = waves,
+                ServerMessage::Map(map) => world.map = map,
+                ServerMessage::Snapshot(snapshot) => {
+                    world.apply_snapshot(&snapshot);
+                    events.extend(snapshot.events.iter().copied());
+                    self.player_id = snapshot.player_id;
+                    self.since_snapshot = 0.0;
+                    while let Some(&(id, _)) = self.pending_inputs.front() {
+                        if id > snapshot.last_input {
+                            break;
+                        }
+                        self.pending_inputs.pop_front();
+                    }
+                    if let Some(player) = Self::find_player(&mut world.players, self.player_id) {
+                        for &(_, action) in &self.pending_inputs {
+                            player.action = action;
+                            player.update(self.tick_duration, &world.config, &mut self.rng);
+                        }
+                    }
+                }
+            }
+        }
+        events
+    }
+
+    /// Sends the local input for the next tick and predicts its outcome.
+    pub fn tick(&mut self, world: &mut World) {
+        let action = match self.player_id {
+            Some(id) => {
+                let action = self.controller.act(id, world);
+                self.next_input += 1;
+                self.connection.send(ClientMessage::Input {
+                    id: self.next_input,
+                    action,
+                });
+                self.pending_inputs.push_back((self.next_input, action));
+                Some(action)
+            }
+            None => None,
+        };
+        for player in &mut world.players {
+            match action {
+                Some(action) if player.owner_id == self.player_id => {
+                    player.action = action;
+                    if let Some(projectile) =
+                        player.update(self.tick_duration, &world.config, &mut self.rng)
+                    {
+                        world
+                            .projectiles
+                            .extend(projectile.weapon.release(projectile, &world.config.weapons));
+                    }
+                }
+                _ => {
+                    if let Some(projectile) = player.projectile.as_mut() {
+                        projectile.update_sparks(
+                            self.tick_duration,
+                            &world.config.arena,
+                            &mut self.rng,
+                        );
+                    }
+                }
+            }
+        }
+        for projectile in &mut world.projectiles {
+            projectile.update_sparks(self.tick_duration, &world.config.arena, &mut self.rng);
+        }
+        world.projectiles.retain(|e| e.alive());
+        for f in &mut world.food {
+            f.update(self.tick_duration);
+        }
+    }
+
+    fn find_player(players: &mut [Player], id: Option<usize>) -> Option<&mut Player> {
+        let id = id?;
+        players
+            .iter_mut()
+            .find(|player| player.owner_id == Some(id))
+    }
+}
@@ -0,0 +1,18 @@
+varying vec4 v_color;
+
+#ifdef VERTEX_SHADER
+attribute vec2 a_pos;
+attribute vec4 a_color;
+uniform mat4 u_view_matrix;
+uniform vec2 u_world_offset;
+void main() {
+    v_color = a_color;
+    gl_Position = u_view_matrix * vec4(u_world_offset + a_pos, 0.0, 1.0);
+}
+#endif
+
+#ifdef FRAGMENT_SHADER
+void main() {
+    gl_FragColor = v_color;
+}
+#endif
@@ -0,0 +1,91 @@
+use crate::*;
+
+/// Kind of enemy, deciding its looks, its stats in `ArchetypesConfig` and how it plays.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Archetype {
+    Grunt,
+    /// Charges huge shots from range.
+    Sniper,
+    /// Small and fast, comes in numbers.
+    Swarmer,
+    /// Slow, but grows quickly by eating.
+    Tank,
+    /// Feeds its allies with its own mass.
+    Healer,
+    /// Comes alone every few waves, see `BossConfig`.
+    Boss,
+}
+
+impl Archetype {
+    pub const ALL: [Self; 6] = [
+        Archetype::Grunt,
+        Archetype::Sniper,
+        Archetype::Swarmer,
+        Archetype::Tank,
+        Archetype::Healer,
+        Archetype::Boss,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Archetype::Grunt => "Grunt",
+            Archetype::Sniper => "Sniper",
+            Archetype::Swarmer => "Swarmer",
+            Archetype::Tank => "Tank",
+            Archetype::Healer => "Healer",
+            Archetype::Boss => "Boss",
+        }
+    }
+
+    pub fn color(self) -> Color<f32> {
+        match self {
+            Archetype::Grunt => Color::RED,
+            Archetype::Sniper => Color::rgb(0.7, 0.3, 1.0),
+            Archetype::Swarmer => Color::rgb(1.0, 0.6, 0.1),
+            Archetype::Tank => Color::rgb(0.6, 0.1, 0.1),
+            Archetype::Healer => Color::rgb(0.2, 0.9, 0.8),
+            Archetype::Boss => Color::rgb(1.0, 0.2, 0.5),
+        }
+    }
+
+    pub fn behaviours(self) -> Vec<(f32, Box<dyn Behaviour>)> {
+        match self {
+            Archetype::Grunt => standard_behaviours(),
+            Archetype::Sniper => vec![
+                (1.0, Box::new(Forage)),
+                (1.2, Box::new(Flee)),
+                (0.3, Box::new(Hunt)),
+                (1.5, Box::new(ChargeShot)),
+                (1.0, Box::new(InterceptShot)),
+                (1.3, Box::new(Kite)),
+                (0.5, Box::new(Regroup)),
+            ],
+            Archetype::Swarmer => vec![
+                (1.0, Box::new(Forage)),
+                (0.5, Box::new(Flee)),
+                (1.5, Box::new(Hunt)),
+                (0.5, Box::new(InterceptShot)),
+                (1.2, Box::new(Regroup)),
+            ],
+            Archetype::Tank => vec![
+                (1.5, Box::new(Forage)),
+                (1.2, Box::new(Hunt)),
+                (0.5, Box::new(ChargeShot)),
+                (0.5, Box::new(InterceptShot)),
+            ],
+            Archetype::Healer => vec![
+                (1.0, Box::new(Forage)),
+                (1.3, Box::new(Flee)),
+                (1.5, Box::new(Feed)),
+                (1.0, Box::new(InterceptShot)),
+                (1.0, Box::new(Regroup)),
+            ],
+            Archetype::Boss => vec![
+                (1.0, Box::new(Forage)),
+                (1.5, Box::new(Hunt)),
+                (1.0, Box::new(ChargeShot)),
+                (0.5, Box::new(InterceptShot)),
+            ],
+        }
+    }
+}
@@ -0,0 +1,274 @@
+use crate::*;
+
+/// One thing a bot may want to do.
+///
+/// Scores are meant to stay between 0 and 1, the bot then weights them
+/// and acts on the highest scoring behaviour.
+pub trait Behaviour {
+    fn score(&self, context: &BotContext) -> f32;
+    fn act(&mut self, context: &BotContext) -> Action;
+}
+
+/// The behaviours of an ordinary enemy.
+pub fn standard_behaviours() -> Vec<(f32, Box<dyn Behaviour>)> {
+    vec![
+        (1.0, Box::new(Forage)),
+        (1.0, Box::new(Flee)),
+        (1.0, Box::new(Hunt)),
+        (1.0, Box::new(ChargeShot)),
+        (1.0, Box::new(InterceptShot)),
+        (1.0, Box::new(Kite)),
+        (1.0, Box::new(Regroup)),
+    ]
+}
+
+/// Goes for the closest food, shooting on the way.
+pub struct Forage;
+
+impl Behaviour for Forage {
+    fn score(&self, context: &BotContext) -> f32 {
+        match context.world.closest_food(context.me.pos) {
+            Some(_) => 0.5,
+            None => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match context.world.closest_food(context.me.pos) {
+            Some(f) => context.delta_to(f.pos),
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Runs from close enemies while small.
+pub struct Flee;
+
+impl Behaviour for Flee {
+    fn score(&self, context: &BotContext) -> f32 {
+        let retreat_distance = context.config().bot.retreat_distance;
+        match context.closest_enemy() {
+            Some(e) if context.me.size < context.skill.retreat_size => {
+                let distance = context.delta_to(e.pos).len();
+                if distance < retreat_distance {
+                    0.5 + 0.5 * (1.0 - distance / retreat_distance)
+                } else {
+                    0.0
+                }
+            }
+            _ => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match context.closest_enemy() {
+            Some(e) => -context.delta_to(e.pos),
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Chases enemies smaller than itself.
+pub struct Hunt;
+
+impl Behaviour for Hunt {
+    fn score(&self, context: &BotContext) -> f32 {
+        match context.closest_enemy() {
+            Some(e) if e.size < context.me.size => 1.0 - e.size / context.me.size,
+            _ => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match context.closest_enemy() {
+            Some(e) => context.delta_to(e.pos + e.vel),
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Stands still to charge a shot at a far enemy.
+pub struct ChargeShot;
+
+impl ChargeShot {
+    const FAR: f32 = 30.0;
+}
+
+impl Behaviour for ChargeShot {
+    fn score(&self, context: &BotContext) -> f32 {
+        match (context.closest_enemy(), context.me.projectile.as_ref()) {
+            (Some(e), Some(_)) => 0.55 * (context.delta_to(e.pos).len() / Self::FAR).min(1.0),
+            _ => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        Action {
+            target_vel: context.steer(vec2(0.0, 0.0)),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Shoots down an incoming projectile with its own.
+pub struct InterceptShot;
+
+impl Behaviour for InterceptShot {
+    fn score(&self, context: &BotContext) -> f32 {
+        let can_shoot = context.me.projectile.is_some() || context.me.size >= context.min_size();
+        match context.threat {
+            Some(ref threat) if can_shoot => threat.urgency,
+            _ => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let threat = match context.threat {
+            Some(threat) => threat,
+            None => return Forage.act(context),
+        };
+        let config = context.config();
+        let shoot = match context.me.projectile {
+            // Release as soon as the shot can cancel the incoming one
+            Some(ref p) if p.size >= threat.size => None,
+            Some(ref p) => {
+                let meet_time = context.config().arena.delta_pos(p.pos, threat.pos).len()
+                    / (config.player.projectile_speed + threat.vel.len());
+                Some(context.aim(threat.pos + threat.vel * meet_time))
+            }
+            None => Some(context.aim(threat.pos)),
+        };
+        Action {
+            target_vel: context.steer(threat.side),
+            shoot,
+            weapon: None,
+        }
+    }
+}
+
+/// Keeps its distance from close enemies while shooting at them.
+pub struct Kite;
+
+impl Kite {
+    const DISTANCE: f32 = 12.0;
+}
+
+impl Behaviour for Kite {
+    fn score(&self, context: &BotContext) -> f32 {
+        match context.closest_enemy() {
+            Some(e)
+                if context.me.size >= context.min_size()
+                    && context.delta_to(e.pos).len() < Self::DISTANCE =>
+            {
+                0.6
+            }
+            _ => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match context.closest_enemy() {
+            Some(e) => {
+                let delta = context.delta_to(e.pos);
+                if delta.len() > 1e-5 {
+                    // Circle around the enemy while backing off
+                    vec2(-delta.y, delta.x).normalize() - delta.normalize()
+                } else {
+                    vec2(0.0, 0.0)
+                }
+            }
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Moves back to teammates after straying away from them.
+pub struct Regroup;
+
+impl Regroup {
+    const DISTANCE: f32 = 15.0;
+}
+
+impl Behaviour for Regroup {
+    fn score(&self, context: &BotContext) -> f32 {
+        match context.closest_teammate() {
+            Some(mate) => {
+                let distance = context.delta_to(mate.pos).len();
+                (0.3 * (distance - Self::DISTANCE) / Self::DISTANCE)
+                    .max(0.0)
+                    .min(0.6)
+            }
+            None => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match context.closest_teammate() {
+            Some(mate) => context.delta_to(mate.pos),
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: context.attack(),
+            weapon: None,
+        }
+    }
+}
+
+/// Stays by the smallest ally to feed it, see `ArchetypeConfig::feed_speed`.
+pub struct Feed;
+
+impl Feed {
+    const RANGE: f32 = 30.0;
+
+    fn ally<'a>(context: &BotContext<'a>) -> Option<&'a Player> {
+        let me = context.me;
+        context
+            .world
+            .players_near(me.pos, Self::RANGE)
+            .filter(|player| {
+                player.team_id == me.team_id
+                    && player.owner_id != me.owner_id
+                    && player.size < me.size
+                    && context.delta_to(player.pos).len() < Self::RANGE
+            })
+            .min_by(|a, b| a.size.partial_cmp(&b.size).unwrap())
+    }
+}
+
+impl Behaviour for Feed {
+    fn score(&self, context: &BotContext) -> f32 {
+        if context.stats.feed_speed <= 0.0 || context.me.size < context.min_size() {
+            return 0.0;
+        }
+        match Self::ally(context) {
+            Some(_) => 0.7,
+            None => 0.0,
+        }
+    }
+    fn act(&mut self, context: &BotContext) -> Action {
+        let target_vel = match Self::ally(context) {
+            Some(ally) => context.delta_to(ally.pos),
+            None => vec2(0.0, 0.0),
+        };
+        Action {
+            target_vel: context.steer(target_vel),
+            shoot: None,
+            weapon: None,
+        }
+    }
+}
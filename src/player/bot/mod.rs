@@ -0,0 +1,358 @@
+use crate::*;
+
+mod behaviour;
+
+pub use behaviour::*;
+
+use std::collections::VecDeque;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Normal,
+    Hard,
+    Nightmare,
+}
+
+impl Default for Difficulty {
+    fn default() -> Self {
+        Difficulty::Normal
+    }
+}
+
+impl Difficulty {
+    pub const ALL: [Self; 4] = [
+        Difficulty::Easy,
+        Difficulty::Normal,
+        Difficulty::Hard,
+        Difficulty::Nightmare,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Normal => "Normal",
+            Difficulty::Hard => "Hard",
+            Difficulty::Nightmare => "Nightmare",
+        }
+    }
+
+    /// `levels` steps harder, stopping at the hardest one.
+    pub fn harder(self, levels: usize) -> Self {
+        let index = Self::ALL.iter().position(|&d| d == self).unwrap();
+        Self::ALL[(index + levels).min(Self::ALL.len() - 1)]
+    }
+}
+
+/// Enemy as seen by a bot.
+#[derive(Debug, Copy, Clone)]
+pub struct Sighting {
+    pub id: usize,
+    pub pos: Vec2<f32>,
+    pub vel: Vec2<f32>,
+    pub size: f32,
+}
+
+/// A projectile that is going to hit the bot if it keeps its course.
+#[derive(Debug, Copy, Clone)]
+pub struct Threat {
+    pub pos: Vec2<f32>,
+    pub vel: Vec2<f32>,
+    pub size: f32,
+    /// Seconds until the hit.
+    pub time: f32,
+    /// Direction to step to avoid it.
+    pub side: Vec2<f32>,
+    /// From 0 to 1, higher the sooner the hit.
+    pub urgency: f32,
+}
+
+/// Everything a behaviour gets to look at.
+pub struct BotContext<'a> {
+    pub world: &'a World,
+    pub me: &'a Player,
+    pub skill: &'a BotSkill,
+    pub stats: ArchetypeConfig,
+    /// Enemies in sight where the bot believes they are, closest first.
+    pub enemies: Vec<Sighting>,
+    pub threat: Option<Threat>,
+    aim_error: f32,
+}
+
+impl<'a> BotContext<'a> {
+    pub fn config(&self) -> &'a Config {
+        &self.world.config
+    }
+
+    pub fn delta_to(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        self.config().arena.delta_pos(self.me.pos, pos)
+    }
+
+    pub fn closest_enemy(&self) -> Option<&Sighting> {
+        self.enemies.first()
+    }
+
+    /// Size below which the bot does not start charging a shot.
+    pub fn min_size(&self) -> f32 {
+        self.config().bot.min_size * self.stats.size
+    }
+
+    /// Closest player of the same team.
+    pub fn closest_teammate(&self) -> Option<&'a Player> {
+        let me = self.me;
+        self.world.closest_player(me.pos, |player| {
+            player.team_id == me.team_id && player.owner_id != me.owner_id
+        })
+    }
+
+    /// Movement towards `target_vel`, stepping aside from incoming shots as the skill allows,
+    /// and sliding along obstacles instead of running into them.
+    pub fn steer(&self, target_vel: Vec2<f32>) -> Vec2<f32> {
+        let target_vel = match self.threat {
+            Some(ref threat) => {
+                let weight = self.skill.dodging * threat.urgency;
+                target_vel.clamp(1.0) * (1.0 - weight) + threat.side * weight
+            }
+            None => target_vel,
+        };
+        let me = self.me;
+        match self.world.map.contact(
+            me.pos,
+            me.size + BotController::OBSTACLE_MARGIN,
+            &self.config().arena,
+        ) {
+            Some((contact, _)) => {
+                let along = Vec2::dot(target_vel, contact.normal);
+                if along >= 0.0 {
+                    return target_vel;
+                }
+                // Keep the speed, going around when heading straight at it
+                let slide = target_vel - contact.normal * along;
+                if slide.len() > 1e-5 {
+                    slide.normalize() * target_vel.len()
+                } else {
+                    contact.normal.rotate_90() * target_vel.len()
+                }
+            }
+            None => target_vel,
+        }
+    }
+
+    /// Where to aim for the projectile to reach `pos`, given the bot's aim error.
+    pub fn aim(&self, pos: Vec2<f32>) -> Vec2<f32> {
+        let (sin, cos) = self.aim_error.sin_cos();
+        let v = self.delta_to(pos);
+        self.me.pos + vec2(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+    }
+
+    /// Whether the projectile being charged would still be big enough
+    /// after flying to `pos`, so it is time to release it.
+    pub fn shot_ready(&self, pos: Vec2<f32>) -> bool {
+        let config = self.config();
+        match self.me.projectile {
+            Some(ref p) => {
+                let hit_time =
+                    config.arena.delta_pos(p.pos, pos).len() / config.player.projectile_speed;
+                p.size - config.projectile.death_speed * hit_time
+                    > config.bot.shot_hit_size * self.stats.shot_size
+            }
+            None => false,
+        }
+    }
+
+    /// The usual way to shoot at the closest enemy: start charging when big enough,
+    /// lead the target while charging and release once the shot is strong enough.
+    pub fn attack(&self) -> Option<Vec2<f32>> {
+        let config = self.config();
+        let e = self.closest_enemy()?;
+        match self.me.projectile {
+            Some(ref p) => {
+                if self.shot_ready(e.pos) {
+                    None
+                } else {
+                    let hit_time = self.config().arena.delta_pos(p.pos, e.pos).len()
+                        / config.player.projectile_speed;
+                    Some(self.aim(e.pos + e.vel * hit_time * self.skill.lead_accuracy))
+                }
+            }
+            None => {
+                if self.me.size < self.min_size() {
+                    None
+                } else {
+                    Some(self.aim(e.pos))
+                }
+            }
+        }
+    }
+}
+
+/// Picks the highest scoring of its behaviours every tick.
+///
+/// Different personalities are made of different behaviours and weights.
+pub struct BotController {
+    difficulty: Difficulty,
+    behaviours: Vec<(f32, Box<dyn Behaviour>)>,
+    rng: StdRng,
+    /// Enemies seen on previous ticks, oldest first.
+    memory: VecDeque<Vec<Sighting>>,
+    /// Angle by which the shot being charged misses.
+    aim_error: f32,
+}
+
+impl BotController {
+    /// How far ahead in seconds bots look for incoming projectiles.
+    const DODGE_LOOKAHEAD: f32 = 1.5;
+    /// How close to an obstacle bots start steering around it.
+    const OBSTACLE_MARGIN: f32 = 1.0;
+
+    /// A bot with the standard set of behaviours.
+    pub fn new(difficulty: Difficulty, seed: u64) -> Self {
+        Self::with_behaviours(difficulty, seed, standard_behaviours())
+    }
+
+    /// Bots draw their mistakes from their own `seed`ed generator,
+    /// so a match still plays out the same from the same seed.
+    pub fn with_behaviours(
+        difficulty: Difficulty,
+        seed: u64,
+        behaviours: Vec<(f32, Box<dyn Behaviour>)>,
+    ) -> Self {
+        Self {
+            difficulty,
+            behaviours,
+            rng: StdRng::seed_from_u64(seed),
+            memory: VecDeque::new(),
+            aim_error: 0.0,
+        }
+    }
+
+    /// Remembers where enemies are now, returning where they were `reaction_delay` ago.
+    fn perceive(&mut self, me: &Player, world: &World, skill: &BotSkill) -> Vec<Sighting> {
+        let arena = &world.config.arena;
+        let sight_range = world.config.bot.sight_range;
+        self.memory.push_back(
+            world
+                .players_near(me.pos, sight_range)
+                .filter(|player| {
+                    player.team_id != me.team_id
+                        && arena.delta_pos(me.pos, player.pos).len() <= sight_range
+                        && world.line_of_sight(me.pos, player.pos)
+                })
+                .map(|player| Sighting {
+                    id: player.owner_id.unwrap(),
+                    pos: player.pos,
+                    vel: player.vel,
+                    size: player.size,
+                })
+                .collect(),
+        );
+        let delay = (skill.reaction_delay / world.config.tick_duration()).round() as usize;
+        while self.memory.len() > delay + 1 {
+            self.memory.pop_front();
+        }
+        let mut seen = self.memory.front().unwrap().clone();
+        seen.retain(|e| world.player(e.id).is_some());
+        seen.sort_by(|a, b| {
+            arena
+                .delta_pos(me.pos, a.pos)
+                .len()
+                .partial_cmp(&arena.delta_pos(me.pos, b.pos).len())
+                .unwrap()
+        });
+        seen
+    }
+
+    /// The most dangerous projectile that is going to hit if the bot keeps its course.
+    fn threat(me: &Player, world: &World) -> Option<Threat> {
+        let config = &world.config;
+        let death_speed = config.projectile.death_speed;
+        let mut result: Option<Threat> = None;
+        // Nothing further away can reach us within the lookahead
+        let reach = Self::DODGE_LOOKAHEAD * (world.max_projectile_speed() + me.vel.len()) + me.size;
+        for e in world.projectiles_near(me.pos, reach) {
+            if e.owner_id == me.owner_id || e.size <= 0.0 {
+                continue;
+            }
+            let vel = e.vel - me.vel;
+            let speed_sqr = Vec2::dot(vel, vel);
+            if speed_sqr < 1e-5 {
+                continue;
+            }
+            let lifetime = if death_speed > 0.0 {
+                e.size / death_speed
+            } else {
+                f32::INFINITY
+            };
+            let horizon = lifetime.min(Self::DODGE_LOOKAHEAD);
+            let pos = config.arena.delta_pos(me.pos, e.pos);
+            // The shot may come at us across the world's edge, unless there are walls
+            let images = if config.arena.wraps() { -1..=1 } else { 0..=0 };
+            for dx in images.clone() {
+                for dy in images.clone() {
+                    let pos = pos + vec2(dx as f32, dy as f32) * 2.0 * config.arena.size;
+                    let time = -Vec2::dot(pos, vel) / speed_sqr;
+                    if time < 0.0 || time > horizon {
+                        continue;
+                    }
+                    let miss = pos + vel * time;
+                    if miss.len() >= me.size + e.size - death_speed * time {
+                        continue;
+                    }
+                    let urgency = 1.0 - time / Self::DODGE_LOOKAHEAD;
+                    if result.map_or(false, |best| best.urgency >= urgency) {
+                        continue;
+                    }
+                    let side = vec2(-vel.y, vel.x).normalize();
+                    let side = if Vec2::dot(side, miss) > 0.0 {
+                        -side
+                    } else {
+                        side
+                    };
+                    result = Some(Threat {
+                        pos: e.pos,
+                        vel: e.vel,
+                        size: e.size,
+                        time,
+                        side,
+                        urgency,
+                    });
+                }
+            }
+        }
+        result
+    }
+}
+
+impl Controller for BotController {
+    fn act(&mut self, self_id: usize, world: &World) -> Action {
+        let me = world.player(self_id).unwrap();
+        let skill = world.config.bot.skill(self.difficulty);
+        if me.projectile.is_none() {
+            // Kept for the whole shot once charging starts
+            self.aim_error = self.rng.gen_range(-skill.aim_error..=skill.aim_error);
+        }
+        let context = BotContext {
+            world,
+            me,
+            skill,
+            stats: me.stats(&world.config),
+            enemies: self.perceive(me, world, skill),
+            threat: Self::threat(me, world),
+            aim_error: self.aim_error,
+        };
+        let mut best: Option<(f32, &mut Box<dyn Behaviour>)> = None;
+        for (weight, behaviour) in &mut self.behaviours {
+            let score = *weight * behaviour.score(&context);
+            if best
+                .as_ref()
+                .map_or(true, |&(best_score, _)| score > best_score)
+            {
+                best = Some((score, behaviour));
+            }
+        }
+        match best {
+            Some((_, behaviour)) => behaviour.act(&context),
+            None => default(),
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::*;
+
+pub use gilrs::GamepadId;
+
+/// Connected gamepads, shared by the game and every `GamepadController`.
+pub struct Gamepads {
+    /// `None` if gamepads are not supported here.
+    gilrs: Option<gilrs::Gilrs>,
+    /// Gamepads chosen by the local players of the current match, in their order.
+    claimed: Vec<GamepadId>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        // Deadzones are applied by the controller, as set in the config
+        let gilrs = match gilrs::GilrsBuilder::new()
+            .with_default_filters(false)
+            .build()
+        {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                error!("Gamepads are not available: {}", e);
+                None
+            }
+        };
+        Self {
+            gilrs,
+            claimed: Vec::new(),
+        }
+    }
+
+    /// Sets which gamepads the local players chose, so that none takes another's.
+    pub fn claim(&mut self, ids: Vec<GamepadId>) {
+        self.claimed = ids;
+    }
+
+    /// Processes pending input, picking up gamepads plugged in or out.
+    /// Has to be called every frame.
+    pub fn update(&mut self) {
+        let gilrs = match self.gilrs.as_mut() {
+            Some(gilrs) => gilrs,
+            None => return,
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                gilrs::EventType::Connected => {
+                    info!("Gamepad connected: {}", gilrs.gamepad(event.id).name());
+                }
+                gilrs::EventType::Disconnected => {
+                    info!("Gamepad disconnected: {}", gilrs.gamepad(event.id).name());
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Ids and names of the connected gamepads.
+    pub fn connected(&self) -> Vec<(GamepadId, String)> {
+        match self.gilrs {
+            Some(ref gilrs) => gilrs
+                .gamepads()
+                .map(|(id, gamepad)| (id, gamepad.name().to_owned()))
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// The `preferred` gamepad, or if it is unplugged, one that nobody chose.
+    /// Players who lost their gamepads take the spare ones in order, never the same.
+    fn get(&self, preferred: Option<GamepadId>) -> Option<gilrs::Gamepad> {
+        let gilrs = self.gilrs.as_ref()?;
+        if let Some(gamepad) = preferred.and_then(|id| gilrs.connected_gamepad(id)) {
+            return Some(gamepad);
+        }
+        let lost: Vec<_> = self
+            .claimed
+            .iter()
+            .filter(|&&id| gilrs.connected_gamepad(id).is_none())
+            .collect();
+        let index = preferred
+            .and_then(|id| lost.iter().position(|&&lost| lost == id))
+            .unwrap_or(0);
+        gilrs
+            .gamepads()
+            .filter(|(id, _)| !self.claimed.contains(id))
+            .nth(index)
+            .map(|(_, gamepad)| gamepad)
+    }
+}
+
+/// Left stick to move, right stick to aim, right trigger to charge
+/// and bumpers to switch weapons.
+pub struct GamepadController {
+    gamepads: Rc<RefCell<Gamepads>>,
+    /// Any connected gamepad is used if `None`, or if this one is unplugged.
+    device: Option<GamepadId>,
+    /// Last direction the right stick was pushed in, kept when it is let go.
+    aim: Vec2<f32>,
+    bumpers_pressed: (bool, bool),
+}
+
+impl GamepadController {
+    /// How far from the player the aim is put, only its direction matters.
+    const AIM_DISTANCE: f32 = 10.0;
+
+    pub fn new(gamepads: &Rc<RefCell<Gamepads>>, device: Option<GamepadId>) -> Self {
+        Self {
+            gamepads: gamepads.clone(),
+            device,
+            aim: vec2(1.0, 0.0),
+            bumpers_pressed: (false, false),
+        }
+    }
+
+    /// Stick position with a radial deadzone, rescaled to still reach 1 at the rim.
+    fn stick(gamepad: &gilrs::Gamepad, x: gilrs::Axis, y: gilrs::Axis, deadzone: f32) -> Vec2<f32> {
+        let v = vec2(gamepad.value(x), gamepad.value(y));
+        let len = v.len();
+        if len <= deadzone {
+            return vec2(0.0, 0.0);
+        }
+        let magnitude = ((len - deadzone) / (1.0 - deadzone)).min(1.0);
+        v / len * magnitude
+    }
+}
+
+impl Controller for GamepadController {
+    fn act(&mut self, self_id: usize, world: &World) -> Action {
+        let config = &world.config.gamepad;
+        let gamepads = self.gamepads.borrow();
+        let gamepad = match gamepads.get(self.device) {
+            Some(gamepad) => gamepad,
+            None => return default(),
+        };
+        let target_vel = Self::stick(
+            &gamepad,
+            gilrs::Axis::LeftStickX,
+            gilrs::Axis::LeftStickY,
+            config.deadzone,
+        );
+        let aim = Self::stick(
+            &gamepad,
+            gilrs::Axis::RightStickX,
+            gilrs::Axis::RightStickY,
+            config.deadzone,
+        );
+        if aim.len() > 0.0 {
+            self.aim = aim.normalize();
+        }
+        let me = world.player(self_id);
+        let trigger = gamepad
+            .button_data(gilrs::Button::RightTrigger2)
+            .map_or(0.0, |data| data.value());
+        let shoot = match me {
+            Some(me) if trigger >= config.trigger_threshold => {
+                Some(me.pos + self.aim * Self::AIM_DISTANCE)
+            }
+            _ => None,
+        };
+        let bumpers = (
+            gamepad.is_pressed(gilrs::Button::LeftTrigger),
+            gamepad.is_pressed(gilrs::Button::RightTrigger),
+        );
+        let weapon = me.and_then(|me| {
+            if bumpers.0 && !self.bumpers_pressed.0 {
+                Some(me.weapon.prev())
+            } else if bumpers.1 && !self.bumpers_pressed.1 {
+                Some(me.weapon.next())
+            } else {
+                None
+            }
+        });
+        self.bumpers_pressed = bumpers;
+        Action {
+            target_vel,
+            shoot,
+            weapon,
+        }
+    }
+}
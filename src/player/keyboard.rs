@@ -1,45 +1,156 @@
 use crate::*;
 
+/// Which keys a `KeyboardController` reads, so that two players can share a keyboard.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum KeyboardLayout {
+    /// Keys and buttons from `Bindings` to move and shoot, the mouse to aim,
+    /// 1-5 to switch weapons.
+    KeyboardMouse,
+    /// Arrows to move, right Ctrl to shoot where going, PageUp/PageDown to switch weapons.
+    Arrows,
+}
+
+impl KeyboardLayout {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyboardLayout::KeyboardMouse => "keyboard and mouse",
+            KeyboardLayout::Arrows => "arrow keys",
+        }
+    }
+
+    /// Keys only this layout reads, which the bindings have to keep clear of.
+    pub fn keys(self) -> &'static [geng::Key] {
+        match self {
+            KeyboardLayout::KeyboardMouse => &[],
+            KeyboardLayout::Arrows => &[
+                geng::Key::Up,
+                geng::Key::Down,
+                geng::Key::Left,
+                geng::Key::Right,
+                geng::Key::RCtrl,
+                geng::Key::PageUp,
+                geng::Key::PageDown,
+            ],
+        }
+    }
+}
+
 pub struct KeyboardController {
     context: Rc<Geng>,
+    layout: KeyboardLayout,
+    bindings: Rc<RefCell<Bindings>>,
     mouse_pos: Rc<Cell<Vec2<f32>>>,
+    /// Last direction moved in, where `KeyboardLayout::Arrows` aims.
+    aim: Vec2<f32>,
+    weapon_keys_pressed: (bool, bool),
 }
 
 impl KeyboardController {
-    pub fn new(context: &Rc<Geng>, mouse_pos: &Rc<Cell<Vec2<f32>>>) -> Self {
+    /// How far from the player the aim is put when not using the mouse.
+    const AIM_DISTANCE: f32 = 10.0;
+
+    pub fn new(
+        context: &Rc<Geng>,
+        layout: KeyboardLayout,
+        bindings: &Rc<RefCell<Bindings>>,
+        mouse_pos: &Rc<Cell<Vec2<f32>>>,
+    ) -> Self {
         Self {
             context: context.clone(),
+            layout,
+            bindings: bindings.clone(),
             mouse_pos: mouse_pos.clone(),
+            aim: vec2(1.0, 0.0),
+            weapon_keys_pressed: (false, false),
         }
     }
-}
 
-impl Controller for KeyboardController {
-    fn act(&mut self, _: usize, _: &Game) -> Action {
+    fn movement(&self, up: Input, left: Input, down: Input, right: Input) -> Vec2<f32> {
+        let window = self.context.window();
         let mut target_vel = vec2(0.0, 0.0);
-        if self.context.window().is_key_pressed(geng::Key::W) {
+        if up.is_pressed(window) {
             target_vel.y += 1.0;
         }
-        if self.context.window().is_key_pressed(geng::Key::A) {
+        if left.is_pressed(window) {
             target_vel.x -= 1.0;
         }
-        if self.context.window().is_key_pressed(geng::Key::S) {
+        if down.is_pressed(window) {
             target_vel.y -= 1.0;
         }
-        if self.context.window().is_key_pressed(geng::Key::D) {
+        if right.is_pressed(window) {
             target_vel.x += 1.0;
         }
+        target_vel
+    }
+
+    fn act_keyboard_mouse(&self) -> Action {
+        let bindings = self.bindings.borrow();
+        let window = self.context.window();
+        let target_vel = self.movement(
+            bindings.move_up,
+            bindings.move_left,
+            bindings.move_down,
+            bindings.move_right,
+        );
+        let weapon = Bindings::WEAPON_KEYS
+            .iter()
+            .position(|&key| window.is_key_pressed(key))
+            .map(|index| Weapon::ALL[index]);
         Action {
             target_vel,
-            shoot: if self
-                .context
-                .window()
-                .is_button_pressed(geng::MouseButton::Left)
-            {
+            shoot: if bindings.is_pressed(Control::Shoot, window) {
                 Some(self.mouse_pos.get())
             } else {
                 None
             },
+            weapon,
+        }
+    }
+
+    fn act_arrows(&mut self, me: Option<&Player>) -> Action {
+        let window = self.context.window();
+        let target_vel = self.movement(
+            Input::Key(geng::Key::Up),
+            Input::Key(geng::Key::Left),
+            Input::Key(geng::Key::Down),
+            Input::Key(geng::Key::Right),
+        );
+        if target_vel.len() > 0.0 {
+            self.aim = target_vel.normalize();
+        }
+        let shoot = match me {
+            Some(me) if window.is_key_pressed(geng::Key::RCtrl) => {
+                Some(me.pos + self.aim * Self::AIM_DISTANCE)
+            }
+            _ => None,
+        };
+        let weapon_keys = (
+            window.is_key_pressed(geng::Key::PageDown),
+            window.is_key_pressed(geng::Key::PageUp),
+        );
+        let weapon = me.and_then(|me| {
+            if weapon_keys.0 && !self.weapon_keys_pressed.0 {
+                Some(me.weapon.prev())
+            } else if weapon_keys.1 && !self.weapon_keys_pressed.1 {
+                Some(me.weapon.next())
+            } else {
+                None
+            }
+        });
+        self.weapon_keys_pressed = weapon_keys;
+        Action {
+            target_vel,
+            shoot,
+            weapon,
+        }
+    }
+}
+
+impl Controller for KeyboardController {
+    fn act(&mut self, self_id: usize, world: &World) -> Action {
+        match self.layout {
+            KeyboardLayout::KeyboardMouse => self.act_keyboard_mouse(),
+            KeyboardLayout::Arrows => self.act_arrows(world.player(self_id)),
         }
     }
 }
@@ -1,30 +1,51 @@
 use crate::*;
 
+mod archetype;
 mod bot;
+#[cfg(not(target_arch = "wasm32"))]
+mod gamepad;
 mod keyboard;
+mod status;
 
+pub use archetype::*;
 pub use bot::*;
+#[cfg(not(target_arch = "wasm32"))]
+pub use gamepad::*;
 pub use keyboard::*;
+pub use status::*;
 
 pub struct Player {
     entity: Entity,
     prev_size: f32,
     pub team_id: usize,
+    /// `None` for human players.
+    pub archetype: Option<Archetype>,
+    pub boss: Option<Boss>,
     pub controller: RefCell<Box<dyn Controller>>,
     pub projectile: Option<Projectile>,
-    pub action: Cell<Action>,
+    /// What the next shot turns into, the one charging keeps its own.
+    pub weapon: Weapon,
+    pub status_effects: Vec<StatusEffect>,
+    pub action: Action,
     time: f32,
-    aim_sound: Option<Sound>,
 }
 
 pub trait Controller {
-    fn act(&mut self, self_id: usize, game: &Game) -> Action;
+    fn act(&mut self, self_id: usize, world: &World) -> Action;
 }
 
-#[derive(Copy, Clone, Debug)]
+impl<T: Controller + ?Sized> Controller for Box<T> {
+    fn act(&mut self, self_id: usize, world: &World) -> Action {
+        (**self).act(self_id, world)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Action {
     target_vel: Vec2<f32>,
     shoot: Option<Vec2<f32>>,
+    /// Switches to another weapon.
+    weapon: Option<Weapon>,
 }
 
 impl Default for Action {
@@ -32,6 +53,7 @@ impl Default for Action {
         Self {
             target_vel: vec2(0.0, 0.0),
             shoot: None,
+            weapon: None,
         }
     }
 }
@@ -50,121 +72,189 @@ impl DerefMut for Player {
 }
 
 impl Player {
-    pub const INITIAL_SIZE: f32 = 1.0;
-    const MAX_SPEED: f32 = 8.0;
-    const MAX_AIMING_SPEED: f32 = 4.0;
-    const ACCELERATION: f32 = 15.0;
-    const PROJECTILE_SPEED: f32 = 25.0;
-    const PROJECTILE_MASS_GAIN_SPEED: f32 = 0.3;
-    const PROJECTILE_COST_SPEED: f32 = 0.1;
-
     pub fn new<T: Controller + 'static>(
+        id: usize,
         pos: Vec2<f32>,
+        size: f32,
         color: Color<f32>,
         controller: T,
         team_id: usize,
     ) -> Self {
-        static NEXT_ID: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(1);
         Self {
             entity: Entity {
-                owner_id: Some({ NEXT_ID.fetch_add(1, std::sync::atomic::Ordering::Relaxed) }),
+                owner_id: Some(id),
                 color,
                 pos,
+                prev_pos: pos,
                 vel: vec2(0.0, 0.0),
-                size: Self::INITIAL_SIZE,
+                size,
             },
-            prev_size: Self::INITIAL_SIZE,
+            prev_size: size,
             team_id,
+            archetype: None,
+            boss: None,
             controller: RefCell::new(Box::new(controller)),
             projectile: None,
-            action: Cell::new(default()),
+            weapon: Weapon::Blob,
+            status_effects: Vec::new(),
+            action: default(),
             time: 0.0,
-            aim_sound: None,
         }
     }
-    pub fn update(&mut self, assets: &Assets, delta_time: f32) -> Option<Projectile> {
+    /// Multipliers of the player's archetype, neutral ones for humans.
+    pub fn stats(&self, config: &Config) -> ArchetypeConfig {
+        match self.archetype {
+            Some(archetype) => config.archetypes.get(archetype).clone(),
+            None => default(),
+        }
+    }
+    pub fn has_status(&self, kind: StatusKind) -> bool {
+        self.status_effects.iter().any(|effect| effect.kind == kind)
+    }
+    /// Starts the effect of `kind`, or stacks it by its `Stacking` rule if already active.
+    pub fn apply_status(&mut self, kind: StatusKind, config: &StatusEffectsConfig) {
+        let effect_config = config.get(kind);
+        match self.status_effects.iter_mut().find(|e| e.kind == kind) {
+            Some(effect) => match effect_config.stacking {
+                Stacking::Refresh => effect.time_left = effect_config.duration,
+                Stacking::Extend => effect.time_left += effect_config.duration,
+                Stacking::Stack => {
+                    effect.stacks = (effect.stacks + 1).min(effect_config.max_stacks);
+                    effect.time_left = effect_config.duration;
+                }
+            },
+            None => self.status_effects.push(StatusEffect {
+                kind,
+                time_left: effect_config.duration,
+                stacks: 1,
+            }),
+        }
+    }
+    pub fn remove_status(&mut self, kind: StatusKind) {
+        self.status_effects.retain(|effect| effect.kind != kind);
+    }
+    /// All the active status effects combined.
+    pub fn modifiers(&self, config: &Config) -> StatModifiers {
+        self.status_effects
+            .iter()
+            .fold(default(), |modifiers: StatModifiers, effect| {
+                modifiers.combine(effect.modifiers(&config.status_effects))
+            })
+    }
+    pub fn update<R: Rng>(
+        &mut self,
+        delta_time: f32,
+        config: &Config,
+        rng: &mut R,
+    ) -> Option<Projectile> {
+        let arena = &config.arena;
+        let player_config = &config.player;
+        let stats = self.stats(config);
+        let modifiers = self.modifiers(config);
+        let speed = stats.speed * modifiers.speed;
+        let acceleration = stats.speed * modifiers.acceleration;
+        let charge_gain = stats.charge * modifiers.charge_gain;
+        let charge_cost = stats.charge * modifiers.charge_cost;
+        for effect in &mut self.status_effects {
+            effect.time_left -= delta_time;
+        }
+        self.status_effects.retain(|effect| effect.time_left > 0.0);
         self.prev_size = self.size;
         self.time += delta_time;
-        let mut action = self.action.get();
-        action.target_vel = action.target_vel.clamp(1.0) * Self::MAX_SPEED;
+        let mut action = self.action;
+        if let Some(weapon) = action.weapon {
+            self.weapon = weapon;
+        }
+        action.target_vel = action.target_vel.clamp(1.0) * player_config.max_speed * speed;
         if action.shoot.is_some() {
-            action.target_vel = action.target_vel.clamp(Self::MAX_AIMING_SPEED);
+            action.target_vel = action
+                .target_vel
+                .clamp(player_config.max_aiming_speed * speed);
         }
         let delta_vel = action.target_vel - self.vel;
-        self.vel += delta_vel.clamp(Self::ACCELERATION * delta_time);
-        self.entity.update(delta_time);
+        self.vel += delta_vel.clamp(player_config.acceleration * acceleration * delta_time);
+        self.entity.update(delta_time, arena);
         if let Some(e) = self.projectile.as_mut() {
-            e.update(delta_time);
+            e.update(delta_time, arena, rng);
         }
         if let Some(target) = action.shoot {
-            if let Some(ref sound) = self.aim_sound {
-                sound.set_pos(self.pos);
-            } else {
-                self.aim_sound = Some(play_sound(&assets.aim, self.pos));
-            }
-            if self.projectile.is_none() {
-                self.projectile = Some(Projectile::new(
-                    self.owner_id,
-                    mix(self.color, Color::WHITE),
-                ));
+            let just_started = self.projectile.is_none();
+            if just_started {
+                let mut projectile = Projectile::new(self.owner_id, mix(self.color, Color::WHITE));
+                projectile.weapon = self.weapon;
+                self.projectile = Some(projectile);
             }
             let projectile = self.projectile.as_mut().unwrap();
             let e = &mut self.entity;
 
-            projectile.pos = e.pos + Game::delta_pos(e.pos, target).clamp(e.size);
-            projectile.vel = Game::delta_pos(e.pos, target).normalize() * Self::PROJECTILE_SPEED;
-            projectile.add_mass(Self::PROJECTILE_MASS_GAIN_SPEED * delta_time);
-            e.add_mass(-Self::PROJECTILE_COST_SPEED * delta_time);
+            projectile.pos = e.pos + arena.delta_pos(e.pos, target).clamp(e.size);
+            if just_started {
+                projectile.prev_pos = projectile.pos;
+            }
+            projectile.vel = arena.delta_pos(e.pos, target).normalize()
+                * player_config.projectile_speed
+                * modifiers.projectile_speed;
+            projectile
+                .add_mass(player_config.projectile_mass_gain_speed * charge_gain * delta_time);
+            e.add_mass(-player_config.projectile_cost_speed * charge_cost * delta_time);
             None
         } else {
-            if let Some(ref sound) = self.aim_sound {
-                sound.stop();
-            }
-            self.aim_sound = None;
-            let result = self.projectile.take();
-            if let Some(ref e) = result {
-                play_sound(&assets.shoot, e.pos);
-            }
-            result
+            self.projectile.take()
         }
     }
 
-    pub fn draw(&self, particles: &mut Vec<ParticleInstance>) {
+    pub fn draw(&self, alpha: f32, particles: &mut Vec<ParticleInstance>) {
         if let Some(e) = self.projectile.as_ref() {
-            e.draw(particles);
+            e.draw(alpha, particles);
         }
+        let pos = self.render_pos(alpha);
         particles.push(ParticleInstance {
-            i_pos: self.pos,
+            i_pos: pos,
             i_size: self.size,
             i_color: mix(self.color, Color::BLACK),
         });
         particles.push(ParticleInstance {
-            i_pos: self.pos,
+            i_pos: pos,
             i_size: self.size * 0.9,
             i_color: self.color,
         });
         particles.push(ParticleInstance {
-            i_pos: self.pos,
+            i_pos: pos,
             i_size: self.size * 0.9 * {
                 ((self.time * 5.0).sin() * 0.5 + 0.5).powf(3.0) * 0.5 + 0.5
             },
             i_color: Color::rgba(1.0, 1.0, 1.0, 0.1),
-        })
+        });
+        self.draw_status_effects(pos, particles);
     }
 
-    pub fn act(&self, game: &Game) {
-        self.action.set(
-            self.controller
-                .borrow_mut()
-                .act(self.owner_id.unwrap(), game),
-        );
+    /// A ring of dots circling the player, one per stack of every effect,
+    /// blinking during the last second.
+    fn draw_status_effects(&self, pos: Vec2<f32>, particles: &mut Vec<ParticleInstance>) {
+        const ORBIT: f32 = 1.4;
+        const DOT_SIZE: f32 = 0.15;
+        let count: usize = self.status_effects.iter().map(|e| e.stacks).sum();
+        let dots = self
+            .status_effects
+            .iter()
+            .flat_map(|effect| std::iter::repeat(effect).take(effect.stacks));
+        for (i, effect) in dots.enumerate() {
+            let angle = self.time * 2.0 + 2.0 * std::f32::consts::PI * i as f32 / count as f32;
+            let mut color = effect.kind.color();
+            if effect.time_left < 1.0 {
+                color.a = (effect.time_left * 20.0).sin() * 0.5 + 0.5;
+            }
+            particles.push(ParticleInstance {
+                i_pos: pos + vec2(angle.cos(), angle.sin()) * self.size * ORBIT,
+                i_size: (self.size * DOT_SIZE).max(DOT_SIZE),
+                i_color: color,
+            });
+        }
     }
-}
 
-impl Drop for Player {
-    fn drop(&mut self) {
-        if let Some(ref sound) = self.aim_sound {
-            sound.stop();
-        }
+    pub fn act(&self, world: &World) -> Action {
+        self.controller
+            .borrow_mut()
+            .act(self.owner_id.unwrap(), world)
     }
 }
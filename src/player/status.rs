@@ -0,0 +1,46 @@
+use crate::*;
+
+/// Temporary change to a player, see `StatusEffectsConfig` for what each one does.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StatusKind {
+    Speed,
+    RapidCharge,
+    CheapShots,
+    Shield,
+    Poison,
+}
+
+impl StatusKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            StatusKind::Speed => "speed",
+            StatusKind::RapidCharge => "rapid charge",
+            StatusKind::CheapShots => "cheap shots",
+            StatusKind::Shield => "shield",
+            StatusKind::Poison => "poison",
+        }
+    }
+
+    pub fn color(self) -> Color<f32> {
+        match self {
+            StatusKind::Speed => Color::rgb(1.0, 1.0, 0.0),
+            StatusKind::RapidCharge => Color::rgb(1.0, 0.5, 0.0),
+            StatusKind::CheapShots => Color::rgb(0.0, 0.5, 1.0),
+            StatusKind::Shield => Color::WHITE,
+            StatusKind::Poison => Color::rgb(0.6, 0.0, 1.0),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub struct StatusEffect {
+    pub kind: StatusKind,
+    pub time_left: f32,
+    pub stacks: usize,
+}
+
+impl StatusEffect {
+    pub fn modifiers(&self, config: &StatusEffectsConfig) -> StatModifiers {
+        config.get(self.kind).modifiers.stacked(self.stacks)
+    }
+}
@@ -4,8 +4,11 @@ pub struct Projectile {
     entity: Entity,
     sparks: Vec<(f32, Entity)>,
     next_spark: f32,
-    prev_alive: Cell<bool>,
+    prev_alive: bool,
     pub actually_hit: bool,
+    pub weapon: Weapon,
+    /// Players a lance already went through, so that each is only hit once.
+    pub pierced: Vec<usize>,
 }
 
 impl Deref for Projectile {
@@ -32,29 +35,37 @@ impl Projectile {
                 owner_id,
                 color,
                 pos: vec2(0.0, 0.0),
+                prev_pos: vec2(0.0, 0.0),
                 vel: vec2(0.0, 0.0),
                 size: 0.0,
             },
             sparks: Vec::new(),
             next_spark: 0.0,
-            prev_alive: Cell::new(true),
+            prev_alive: true,
             actually_hit: false,
+            weapon: Weapon::Blob,
+            pierced: Vec::new(),
         }
     }
 
-    pub fn alive(&self, assets: &Assets) -> bool {
+    /// Returns `true` only once, on the first call after the projectile ran out of mass.
+    pub fn just_died(&mut self) -> bool {
         let alive = self.size > 0.0;
-        if alive != self.prev_alive.get() {
-            if self.actually_hit {
-                play_sound(&assets.hit, self.pos);
-            }
-            self.prev_alive.set(alive);
-        }
-        alive || !self.sparks.is_empty()
+        let result = self.prev_alive && !alive;
+        self.prev_alive = alive;
+        result
+    }
+
+    pub fn alive(&self) -> bool {
+        self.size > 0.0 || !self.sparks.is_empty()
     }
 
-    pub fn update(&mut self, delta_time: f32) {
-        self.entity.update(delta_time);
+    pub fn update<R: Rng>(&mut self, delta_time: f32, arena: &ArenaConfig, rng: &mut R) {
+        self.entity.update(delta_time, arena);
+        self.update_sparks(delta_time, arena, rng);
+    }
+
+    pub fn update_sparks<R: Rng>(&mut self, delta_time: f32, arena: &ArenaConfig, rng: &mut R) {
         self.next_spark -= delta_time * self.mass();
         while self.next_spark < 0.0 && self.size > 0.0 {
             self.next_spark += 1.0 / Self::SPARK_FREQ;
@@ -64,23 +75,31 @@ impl Projectile {
                     owner_id: None,
                     color: mix(Color::WHITE, self.entity.color),
                     pos: self.entity.pos,
-                    vel: random_circle_point() * Self::SPARK_MAX_SPEED,
-                    size: global_rng().gen_range(self.entity.size / 2.0..=self.entity.size),
+                    prev_pos: self.entity.pos,
+                    vel: random_circle_point(rng) * Self::SPARK_MAX_SPEED,
+                    size: rng.gen_range(self.entity.size / 2.0..=self.entity.size),
                 },
             ))
         }
         for &mut (ref mut t, ref mut e) in &mut self.sparks {
             *t += delta_time;
-            e.update(delta_time);
+            e.update(delta_time, arena);
             e.color.a = (1.0 - *t / Self::SPARK_LIFE) * 0.5;
         }
         self.sparks.retain(|&(t, _)| t < Self::SPARK_LIFE);
     }
 
-    pub fn draw(&self, particles: &mut Vec<ParticleInstance>) {
-        self.entity.draw(particles);
+    pub fn draw(&self, alpha: f32, particles: &mut Vec<ParticleInstance>) {
+        self.entity.draw(alpha, particles);
+        if self.weapon == Weapon::Mine && self.size > 0.0 {
+            particles.push(ParticleInstance {
+                i_pos: self.render_pos(alpha),
+                i_size: self.size * 0.5,
+                i_color: Color::rgba(0.0, 0.0, 0.0, 0.5),
+            });
+        }
         for &(_, ref e) in &self.sparks {
-            e.draw(particles);
+            e.draw(alpha, particles);
         }
     }
 }
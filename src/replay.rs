@@ -0,0 +1,121 @@
+use crate::*;
+
+/// Everything needed to play a match again: the seed, the settings and every action taken.
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub config: Config,
+    pub difficulty: Difficulty,
+    pub waves: WaveScript,
+    pub map: Map,
+    /// Human players added at the start, all playing on the same machine.
+    pub players: usize,
+    ticks: Vec<Vec<(usize, Action)>>,
+    /// Configs reloaded during the match, with the tick they apply from.
+    config_changes: Vec<(usize, Config)>,
+}
+
+impl Replay {
+    const MAGIC: [u8; 4] = *b"LSRP";
+    pub const VERSION: u32 = 16;
+
+    pub fn new(
+        seed: u64,
+        config: Config,
+        difficulty: Difficulty,
+        waves: WaveScript,
+        map: Map,
+        players: usize,
+    ) -> Self {
+        Self {
+            seed,
+            config,
+            difficulty,
+            waves,
+            map,
+            players,
+            ticks: Vec::new(),
+            config_changes: Vec::new(),
+        }
+    }
+
+    pub fn tick_count(&self) -> usize {
+        self.ticks.len()
+    }
+
+    /// Stores the actions for the next tick, `actions` being parallel to `players`.
+    pub fn record(&mut self, players: &[Player], actions: &[Action]) {
+        self.ticks.push(
+            players
+                .iter()
+                .zip(actions)
+                .map(|(player, &action)| (player.owner_id.unwrap(), action))
+                .collect(),
+        );
+    }
+
+    /// Stores a config that applies from the next recorded tick on.
+    pub fn record_config(&mut self, config: &Config) {
+        self.config_changes.push((self.ticks.len(), config.clone()));
+    }
+
+    /// The config to switch to before simulating `tick`, if it was changed then.
+    pub fn config_change(&self, tick: usize) -> Option<&Config> {
+        self.config_changes
+            .iter()
+            .rev()
+            .find(|&&(change_tick, _)| change_tick == tick)
+            .map(|(_, config)| config)
+    }
+
+    pub fn action(&self, tick: usize, player_id: usize) -> Option<Action> {
+        self.ticks
+            .get(tick)?
+            .iter()
+            .find(|&&(id, _)| id == player_id)
+            .map(|&(_, action)| action)
+    }
+
+    pub fn write_to(&self, mut writer: impl Write) -> anyhow::Result<()> {
+        writer.write_all(&Self::MAGIC)?;
+        bincode::serialize_into(&mut writer, &Self::VERSION)?;
+        bincode::serialize_into(&mut writer, self)?;
+        Ok(())
+    }
+
+    pub fn read_from(mut reader: impl Read) -> anyhow::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            anyhow::bail!("Not a replay file");
+        }
+        let version: u32 = bincode::deserialize_from(&mut reader)?;
+        if version != Self::VERSION {
+            anyhow::bail!(
+                "Replay version {} is not supported, expected {}",
+                version,
+                Self::VERSION,
+            );
+        }
+        Ok(bincode::deserialize_from(reader)?)
+    }
+}
+
+/// Plays back the recorded actions of a single player.
+pub struct ReplayController {
+    replay: Rc<Replay>,
+}
+
+impl ReplayController {
+    pub fn new(replay: &Rc<Replay>) -> Self {
+        Self {
+            replay: replay.clone(),
+        }
+    }
+}
+
+impl Controller for ReplayController {
+    fn act(&mut self, self_id: usize, world: &World) -> Action {
+        self.replay.action(world.tick, self_id).unwrap_or_default()
+    }
+}
@@ -0,0 +1,176 @@
+use crate::*;
+
+#[derive(Default)]
+struct Lobby {
+    next_client_id: usize,
+    joined: Vec<(usize, Box<dyn geng::net::Sender<ServerMessage>>)>,
+    messages: Vec<(usize, ClientMessage)>,
+    left: Vec<usize>,
+}
+
+/// Runs the authoritative simulation for networked clients.
+pub struct Server {
+    lobby: Arc<Mutex<Lobby>>,
+}
+
+pub struct ServerClient {
+    id: usize,
+    lobby: Arc<Mutex<Lobby>>,
+}
+
+impl geng::net::Receiver<ClientMessage> for ServerClient {
+    fn handle(&mut self, message: ClientMessage) {
+        self.lobby.lock().unwrap().messages.push((self.id, message));
+    }
+}
+
+impl Drop for ServerClient {
+    fn drop(&mut self) {
+        self.lobby.lock().unwrap().left.push(self.id);
+    }
+}
+
+impl geng::net::server::App for Server {
+    type Client = ServerClient;
+    type ServerMessage = ServerMessage;
+    type ClientMessage = ClientMessage;
+    fn connect(&mut self, sender: Box<dyn geng::net::Sender<ServerMessage>>) -> ServerClient {
+        let mut lobby = self.lobby.lock().unwrap();
+        lobby.next_client_id += 1;
+        let id = lobby.next_client_id;
+        lobby.joined.push((id, sender));
+        ServerClient {
+            id,
+            lobby: self.lobby.clone(),
+        }
+    }
+}
+
+struct Client {
+    sender: Box<dyn geng::net::Sender<ServerMessage>>,
+    player_id: Option<usize>,
+    action: Rc<Cell<Action>>,
+    last_input: u64,
+}
+
+impl Client {
+    fn alive(&self, world: &World) -> bool {
+        match self.player_id {
+            Some(id) => world.players.iter().any(|p| p.owner_id == Some(id)),
+            None => false,
+        }
+    }
+    fn spawn(&mut self, world: &mut World) {
+        self.action = Rc::new(Cell::new(default()));
+        self.player_id = Some(world.add_player(RemoteController::new(&self.action)));
+    }
+}
+
+impl Server {
+    pub const DEFAULT_ADDR: &'static str = "127.0.0.1:1155";
+
+    /// Listens on `addr`, blocking forever.
+    pub fn run(addr: &str, config: Config, waves: WaveScript, map: Map) {
+        let lobby = Arc::new(Mutex::new(Lobby::default()));
+        std::thread::spawn({
+            let lobby = lobby.clone();
+            move || Self::simulate(&lobby, config, waves, map)
+        });
+        geng::net::Server::new(Self { lobby }, addr).run();
+    }
+
+    fn simulate(lobby: &Mutex<Lobby>, config: Config, waves: WaveScript, map: Map) {
+        let tick_duration = config.tick_duration();
+        let mut world = World::empty(global_rng().gen(), config.clone());
+        world.waves = waves.clone();
+        world.map = map.clone();
+        let mut clients = HashMap::<usize, Client>::new();
+        let mut events = Vec::new();
+        let mut next_tick = std::time::Instant::now();
+        loop {
+            {
+                let mut lobby = lobby.lock().unwrap();
+                for (id, mut sender) in lobby.joined.drain(..) {
+                    info!("Client #{} joined", id);
+                    sender.send(ServerMessage::Config(config.clone()));
+                    sender.send(ServerMessage::Waves(waves.clone()));
+                    sender.send(ServerMessage::Map(map.clone()));
+                    let mut client = Client {
+                        sender,
+                        player_id: None,
+                        action: default(),
+                        last_input: 0,
+                    };
+                    client.spawn(&mut world);
+                    clients.insert(id, client);
+                }
+                for (id, message) in lobby.messages.drain(..) {
+                    let client = match clients.get_mut(&id) {
+                        Some(client) => client,
+                        None => continue,
+                    };
+                    match message {
+                        ClientMessage::Input { id, action } => {
+                            if id > client.last_input {
+                                client.last_input = id;
+                                client.action.set(action);
+                            }
+                        }
+                        ClientMessage::Respawn => {
+                            if client.alive(&world) {
+                                continue;
+                            }
+                            if !world.player_alive() {
+                                info!("Everybody is dead, starting a new match");
+                                world = World::empty(global_rng().gen(), config.clone());
+                                world.waves = waves.clone();
+                                world.map = map.clone();
+                                events.clear();
+                                for client in clients.values_mut() {
+                                    client.player_id = None;
+                                }
+                            }
+                            clients.get_mut(&id).unwrap().spawn(&mut world);
+                        }
+                    }
+                }
+                for id in lobby.left.drain(..) {
+                    info!("Client #{} left", id);
+                    if let Some(player_id) = clients.remove(&id).and_then(|c| c.player_id) {
+                        world.remove_player(player_id);
+                    }
+                }
+            }
+
+            let actions = world.act();
+            world.step(tick_duration, &actions);
+            events.extend(world.take_events());
+
+            if world.tick % Snapshot::INTERVAL == 0 {
+                let snapshot = world.snapshot();
+                for client in clients.values_mut() {
+                    let player_id = if client.alive(&world) {
+                        client.player_id
+                    } else {
+                        None
+                    };
+                    client.sender.send(ServerMessage::Snapshot(Snapshot {
+                        player_id,
+                        last_input: client.last_input,
+                        events: events.clone(),
+                        ..snapshot.clone()
+                    }));
+                }
+                events.clear();
+            }
+
+            next_tick += std::time::Duration::from_secs_f32(tick_duration);
+            let now = std::time::Instant::now();
+            if next_tick > now {
+                std::thread::sleep(next_tick - now);
+            } else {
+                next_tick = now;
+            }
+        }
+    }
+}
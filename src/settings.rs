@@ -0,0 +1,111 @@
+use crate::*;
+
+/// How colours are drawn, for players who tell some of them apart poorly.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Palette {
+    Classic,
+    /// Red and green told apart by how blue they are too.
+    RedGreen,
+    /// Every colour as saturated as it gets.
+    HighContrast,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::Classic
+    }
+}
+
+impl Palette {
+    pub const ALL: [Self; 3] = [Palette::Classic, Palette::RedGreen, Palette::HighContrast];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Palette::Classic => "classic",
+            Palette::RedGreen => "red-green safe",
+            Palette::HighContrast => "high contrast",
+        }
+    }
+
+    /// The next palette, going around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&p| p == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    /// What `color` looks like in this palette, alpha is kept.
+    pub fn apply(self, color: Color<f32>) -> Color<f32> {
+        match self {
+            Palette::Classic => color,
+            Palette::RedGreen => Color::rgba(
+                color.r,
+                color.g,
+                clamp(color.b + (color.g - color.r) * 0.7, 0.0..=1.0),
+                color.a,
+            ),
+            Palette::HighContrast => {
+                const SATURATION: f32 = 1.6;
+                let mean = (color.r + color.g + color.b) / 3.0;
+                let saturate = |x: f32| clamp(mean + (x - mean) * SATURATION, 0.0..=1.0);
+                Color::rgba(
+                    saturate(color.r),
+                    saturate(color.g),
+                    saturate(color.b),
+                    color.a,
+                )
+            }
+        }
+    }
+}
+
+/// Preferences of whoever plays on this machine, loaded from `settings.json`.
+///
+/// Every value is optional in the file, missing ones keep their defaults.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// From 0 to 1, of the music's usual volume.
+    pub music_volume: f32,
+    /// From 0 to 1, of the sound effects' usual volume.
+    pub sfx_volume: f32,
+    pub palette: Palette,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            music_volume: 1.0,
+            sfx_volume: 1.0,
+            palette: default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let settings: Self = serde_json::from_str(json)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Invalid settings in {:?}", path))
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let json = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {:?}", path))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        check_fraction("music_volume", self.music_volume)?;
+        check_fraction("sfx_volume", self.sfx_volume)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,119 @@
+use crate::*;
+
+/// Uniform grid over the world, used to find objects near a point
+/// without looking at every one of them. The grid always wraps around;
+/// in a walled arena that only makes queries near the walls a bit wider.
+///
+/// Objects are stored by index into whatever list the grid was built from,
+/// so it has to be rebuilt whenever that list changes.
+pub struct SpatialHash {
+    world_size: f32,
+    cells_per_side: usize,
+    cell_size: f32,
+    cells: Vec<Vec<(usize, Vec2<f32>)>>,
+    max_radius: f32,
+}
+
+impl SpatialHash {
+    pub fn new(arena: &ArenaConfig, cell_size: f32) -> Self {
+        // Cells have to tile the world exactly for wrapping to line up
+        let cells_per_side = ((2.0 * arena.size / cell_size) as usize).max(1);
+        Self {
+            world_size: arena.size,
+            cells_per_side,
+            cell_size: 2.0 * arena.size / cells_per_side as f32,
+            cells: (0..cells_per_side * cells_per_side)
+                .map(|_| Vec::new())
+                .collect(),
+            max_radius: 0.0,
+        }
+    }
+
+    pub fn build<'a>(
+        arena: &ArenaConfig,
+        cell_size: f32,
+        entities: impl IntoIterator<Item = &'a Entity>,
+    ) -> Self {
+        let mut result = Self::new(arena, cell_size);
+        for (index, e) in entities.into_iter().enumerate() {
+            result.insert(index, e.pos, e.swept_radius());
+        }
+        result
+    }
+
+    pub fn insert(&mut self, index: usize, pos: Vec2<f32>, radius: f32) {
+        let cell = self.cell_index(self.cell_coord(pos.x), self.cell_coord(pos.y));
+        self.cells[cell].push((index, pos));
+        self.max_radius = self.max_radius.max(radius);
+    }
+
+    fn cell_coord(&self, x: f32) -> isize {
+        ((x + self.world_size) / self.cell_size).floor() as isize
+    }
+
+    fn cell_index(&self, x: isize, y: isize) -> usize {
+        let n = self.cells_per_side as isize;
+        (x.rem_euclid(n) * n + y.rem_euclid(n)) as usize
+    }
+
+    /// Covered cell coordinates, never visiting the same cell twice.
+    fn cell_range(&self, from: f32, to: f32) -> std::ops::RangeInclusive<isize> {
+        let start = self.cell_coord(from);
+        let end = self
+            .cell_coord(to)
+            .min(start + self.cells_per_side as isize - 1);
+        start..=end
+    }
+
+    /// Indices of all objects that may overlap a circle, in ascending order.
+    pub fn query(&self, pos: Vec2<f32>, radius: f32) -> Vec<usize> {
+        let reach = radius + self.max_radius;
+        let mut result = Vec::new();
+        for x in self.cell_range(pos.x - reach, pos.x + reach) {
+            for y in self.cell_range(pos.y - reach, pos.y + reach) {
+                result.extend(self.cells[self.cell_index(x, y)].iter().map(|&(i, _)| i));
+            }
+        }
+        result.sort_unstable();
+        result
+    }
+
+    /// Index of the object closest to `pos` among those accepted by `filter`.
+    pub fn nearest(
+        &self,
+        pos: Vec2<f32>,
+        arena: &ArenaConfig,
+        mut filter: impl FnMut(usize) -> bool,
+    ) -> Option<usize> {
+        let center_x = self.cell_coord(pos.x);
+        let center_y = self.cell_coord(pos.y);
+        let mut best: Option<(usize, f32)> = None;
+        // Rings of cells around the center, until everything in the next one is farther
+        for ring in 0..=(self.cells_per_side / 2 + 1) as isize {
+            if let Some((_, distance)) = best {
+                if distance <= (ring - 1) as f32 * self.cell_size {
+                    break;
+                }
+            }
+            for x in -ring..=ring {
+                for y in -ring..=ring {
+                    if x.abs() != ring && y.abs() != ring {
+                        continue;
+                    }
+                    for &(index, p) in &self.cells[self.cell_index(center_x + x, center_y + y)] {
+                        if !filter(index) {
+                            continue;
+                        }
+                        let distance = arena.delta_pos(pos, p).len();
+                        if best.map_or(true, |(best_index, best_distance)| {
+                            (distance, index) < (best_distance, best_index)
+                        }) {
+                            best = Some((index, distance));
+                        }
+                    }
+                }
+            }
+        }
+        best.map(|(index, _)| index)
+    }
+}
@@ -0,0 +1,193 @@
+use crate::*;
+
+/// Wave progression, loaded from `waves.json`.
+///
+/// Scripted waves come first, then waves keep coming by the `endless` rule.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveScript {
+    pub waves: Vec<WaveDefinition>,
+    pub endless: EndlessWaves,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaveDefinition {
+    pub groups: Vec<SpawnGroup>,
+    /// Seconds until the next wave, `waves.time_between` if not set.
+    pub time: Option<f32>,
+    /// Multiplies how often food appears during the wave.
+    pub food_rate: f32,
+    /// Skill of the wave's bots, instead of the one picked in the menu and `waves.harder_every`.
+    pub difficulty: Option<Difficulty>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpawnGroup {
+    pub archetype: Archetype,
+    pub count: usize,
+    pub pattern: SpawnPattern,
+    /// Seconds after the start of the wave.
+    pub delay: f32,
+    /// Skill of these bots, instead of the wave's.
+    pub difficulty: Option<Difficulty>,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpawnPattern {
+    /// Anywhere far enough from the players.
+    Random,
+    /// Evenly spread on a circle around a player.
+    Ring,
+    /// Just out of a player's view.
+    Edge,
+    /// All together somewhere far enough from the players.
+    Cluster,
+}
+
+/// Waves after the scripted ones, archetypes being picked by their frequencies.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EndlessWaves {
+    /// Enemies in the first endless wave.
+    pub count: usize,
+    /// Enemies added with every next wave.
+    pub count_growth: usize,
+    /// Every this many endless waves a boss comes instead, 0 for never.
+    pub boss_every: usize,
+    pub pattern: SpawnPattern,
+    pub food_rate: f32,
+}
+
+impl Default for WaveScript {
+    fn default() -> Self {
+        Self {
+            waves: Vec::new(),
+            endless: default(),
+        }
+    }
+}
+
+impl Default for WaveDefinition {
+    fn default() -> Self {
+        Self {
+            groups: Vec::new(),
+            time: None,
+            food_rate: 1.0,
+            difficulty: None,
+        }
+    }
+}
+
+impl Default for SpawnGroup {
+    fn default() -> Self {
+        Self {
+            archetype: Archetype::Grunt,
+            count: 1,
+            pattern: default(),
+            delay: 0.0,
+            difficulty: None,
+        }
+    }
+}
+
+impl Default for SpawnPattern {
+    fn default() -> Self {
+        SpawnPattern::Random
+    }
+}
+
+impl Default for EndlessWaves {
+    fn default() -> Self {
+        Self {
+            count: 1,
+            count_growth: 1,
+            boss_every: 5,
+            pattern: default(),
+            food_rate: 1.0,
+        }
+    }
+}
+
+impl WaveScript {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        let script: Self = serde_json::from_str(json)?;
+        script.validate()?;
+        Ok(script)
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn load(path: impl AsRef<std::path::Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read {:?}", path))?;
+        Self::parse(&json).with_context(|| format!("Invalid wave script in {:?}", path))
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (i, wave) in self.waves.iter().enumerate() {
+            let name = format!("waves[{}]", i);
+            if let Some(time) = wave.time {
+                check_positive(&format!("{}.time", name), time)?;
+            }
+            check_non_negative(&format!("{}.food_rate", name), wave.food_rate)?;
+            for (j, group) in wave.groups.iter().enumerate() {
+                let name = format!("{}.groups[{}]", name, j);
+                if group.count == 0 {
+                    anyhow::bail!("{}.count must be positive", name);
+                }
+                check_non_negative(&format!("{}.delay", name), group.delay)?;
+            }
+        }
+        check_non_negative("endless.food_rate", self.endless.food_rate)?;
+        Ok(())
+    }
+
+    /// Whether the given wave, counting from 1, brings a boss.
+    pub fn is_boss_wave(&self, wave: usize) -> bool {
+        match self.waves.get(wave.max(1) - 1) {
+            Some(definition) => definition
+                .groups
+                .iter()
+                .any(|group| group.archetype == Archetype::Boss),
+            None => match self.endless.boss_every {
+                0 => false,
+                n => (wave - self.waves.len()) % n == 0,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wave(json: &str) -> anyhow::Result<WaveScript> {
+        WaveScript::parse(&format!(r#"{{ "waves": [{}] }}"#, json))
+    }
+
+    #[test]
+    fn wave_and_group_difficulty_are_read() {
+        let script = wave(
+            r#"{
+                "difficulty": "Hard",
+                "groups": [
+                    { "archetype": "Grunt" },
+                    { "archetype": "Sniper", "difficulty": "Easy" }
+                ]
+            }"#,
+        )
+        .unwrap();
+        let wave = &script.waves[0];
+        assert_eq!(wave.difficulty, Some(Difficulty::Hard));
+        assert_eq!(wave.groups[0].difficulty, None);
+        assert_eq!(wave.groups[1].difficulty, Some(Difficulty::Easy));
+    }
+
+    #[test]
+    fn unknown_difficulty_is_rejected() {
+        assert!(wave(r#"{ "difficulty": "Impossible" }"#).is_err());
+        assert!(wave(r#"{ "groups": [{ "difficulty": "hard" }] }"#).is_err());
+    }
+}
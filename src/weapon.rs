@@ -0,0 +1,93 @@
+use crate::*;
+
+/// What a charged shot turns into once released, tuned in `WeaponsConfig`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Weapon {
+    /// A single projectile, flying straight.
+    Blob,
+    /// Several smaller pellets, spread out.
+    Shotgun,
+    /// A fast projectile passing through whatever it hits.
+    Lance,
+    /// A slow projectile steering towards the closest enemy.
+    Homing,
+    /// Stays where it was released until something touches it.
+    Mine,
+}
+
+impl Weapon {
+    pub const ALL: [Self; 5] = [
+        Weapon::Blob,
+        Weapon::Shotgun,
+        Weapon::Lance,
+        Weapon::Homing,
+        Weapon::Mine,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Weapon::Blob => "blob",
+            Weapon::Shotgun => "shotgun",
+            Weapon::Lance => "lance",
+            Weapon::Homing => "homing orb",
+            Weapon::Mine => "mine",
+        }
+    }
+
+    /// The one after this in `ALL`, going around.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|&w| w == self).unwrap();
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+
+    pub fn prev(self) -> Self {
+        let index = Self::ALL.iter().position(|&w| w == self).unwrap();
+        Self::ALL[(index + Self::ALL.len() - 1) % Self::ALL.len()]
+    }
+
+    /// Turns a charged shot into what actually flies, keeping its mass.
+    pub fn release(self, mut e: Projectile, config: &WeaponsConfig) -> Vec<Projectile> {
+        match self {
+            Weapon::Blob => vec![e],
+            Weapon::Shotgun => {
+                let count = config.shotgun_pellets;
+                let size = e.size / (count as f32).sqrt();
+                let spread = config.shotgun_spread.to_radians();
+                (0..count)
+                    .map(|i| {
+                        let angle = if count > 1 {
+                            spread * (i as f32 / (count - 1) as f32 - 0.5)
+                        } else {
+                            0.0
+                        };
+                        let mut pellet = Projectile::new(e.owner_id, e.color);
+                        pellet.weapon = self;
+                        pellet.pos = e.pos;
+                        pellet.prev_pos = e.prev_pos;
+                        pellet.vel = e.vel.rotate(angle);
+                        pellet.size = size;
+                        pellet
+                    })
+                    .collect()
+            }
+            Weapon::Lance => {
+                e.vel *= config.lance_speed;
+                vec![e]
+            }
+            Weapon::Homing => {
+                e.vel *= config.homing_speed;
+                vec![e]
+            }
+            Weapon::Mine => {
+                e.vel = vec2(0.0, 0.0);
+                vec![e]
+            }
+        }
+    }
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon::Blob
+    }
+}
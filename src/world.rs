@@ -0,0 +1,1121 @@
+use crate::*;
+
+#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+pub enum Event {
+    Shoot { pos: Vec2<f32> },
+    Hit { pos: Vec2<f32> },
+    Death { pos: Vec2<f32> },
+    Heal { pos: Vec2<f32> },
+    BossSpawn { pos: Vec2<f32> },
+    BossDeath { pos: Vec2<f32> },
+}
+
+pub struct World {
+    pub players: Vec<Player>,
+    pub projectiles: Vec<Projectile>,
+    pub food: Vec<Food>,
+    pub next_food: f32,
+    pub next_wave_timer: f32,
+    pub next_wave: usize,
+    pub time_played: f32,
+    pub kills: usize,
+    pub waves_finished: usize,
+    pub start: f32,
+    pub tick: usize,
+    pub seed: u64,
+    pub config: Config,
+    /// Skill of the bots in the first wave.
+    pub difficulty: Difficulty,
+    pub ledger: MassLedger,
+    pub waves: WaveScript,
+    pub map: Map,
+    /// Multiplies how often food appears, set by the current wave.
+    food_rate: f32,
+    /// Groups of the current wave still waiting for their delay.
+    pending_spawns: Vec<SpawnGroup>,
+    player_grid: SpatialHash,
+    /// Index in `players` of every player id.
+    player_indices: HashMap<usize, usize>,
+    food_grid: SpatialHash,
+    projectile_grid: SpatialHash,
+    /// Speed of the fastest projectile in flight.
+    max_projectile_speed: f32,
+    rng: StdRng,
+    cosmetic_rng: StdRng,
+    next_id: usize,
+    events: Vec<Event>,
+}
+
+impl World {
+    const GRID_CELL_SIZE: f32 = 5.0;
+    /// How far apart, beyond touching, a healer can still feed an ally.
+    const FEED_REACH: f32 = 1.0;
+    /// How far from its center a `SpawnPattern::Cluster` spreads.
+    const CLUSTER_RADIUS: f32 = 3.0;
+    /// How many times a spawn is pushed out of obstacles before another place is picked.
+    const PUSH_OUT_ATTEMPTS: usize = 4;
+    /// How many random places `spawn_pos` tries before settling for the least bad one.
+    const SPAWN_ATTEMPTS: usize = 1000;
+
+    /// Same seed and same actions give the same match.
+    /// Purely visual randomness is drawn from a separate stream,
+    /// so it never affects gameplay.
+    pub fn new<T: Controller + 'static>(seed: u64, config: Config, controller: T) -> Self {
+        let mut world = Self::empty(seed, config);
+        world.add_player(controller);
+        world
+    }
+
+    /// A world with no players in it yet.
+    pub fn empty(seed: u64, config: Config) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let cosmetic_rng = StdRng::seed_from_u64(rng.gen());
+        Self {
+            players: Vec::new(),
+            projectiles: Vec::new(),
+            food: Vec::new(),
+            next_food: 0.0,
+            next_wave_timer: config.waves.pause,
+            next_wave: 1,
+            time_played: 0.0,
+            kills: 0,
+            waves_finished: 0,
+            start: 0.0,
+            tick: 0,
+            seed,
+            difficulty: default(),
+            ledger: default(),
+            waves: default(),
+            map: default(),
+            food_rate: 1.0,
+            pending_spawns: Vec::new(),
+            player_grid: SpatialHash::new(&config.arena, Self::GRID_CELL_SIZE),
+            player_indices: HashMap::new(),
+            food_grid: SpatialHash::new(&config.arena, Self::GRID_CELL_SIZE),
+            projectile_grid: SpatialHash::new(&config.arena, Self::GRID_CELL_SIZE),
+            max_projectile_speed: 0.0,
+            config,
+            rng,
+            cosmetic_rng,
+            next_id: 1,
+            events: Vec::new(),
+        }
+    }
+
+    fn human_color(index: usize) -> Color<f32> {
+        match index % 4 {
+            0 => Color::rgb(0.5, 0.5, 1.0),
+            1 => Color::rgb(0.5, 1.0, 0.5),
+            2 => Color::rgb(1.0, 1.0, 0.5),
+            _ => Color::rgb(0.5, 1.0, 1.0),
+        }
+    }
+
+    /// Spawns a new human-team player near the center, returning its id.
+    pub fn add_player<T: Controller + 'static>(&mut self, controller: T) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        // Teammates start side by side, each in their own colour
+        let humans = self
+            .players
+            .iter()
+            .filter(|player| player.team_id == 0)
+            .count();
+        let pos = if humans == 0 {
+            vec2(0.0, 0.0)
+        } else {
+            let angle = humans as f32 * std::f32::consts::PI / 2.0;
+            vec2(angle.cos(), angle.sin()) * self.config.player.initial_size * 3.0
+        };
+        let pos = self.free_pos(pos, self.config.player.initial_size);
+        let player = Player::new(
+            id,
+            pos,
+            self.config.player.initial_size,
+            Self::human_color(humans),
+            controller,
+            0,
+        );
+        let mass = player.mass();
+        self.ledger.record(Some(id), |b| b.spawned += mass);
+        self.players.push(player);
+        self.update_grids();
+        id
+    }
+
+    pub fn remove_player(&mut self, id: usize) {
+        let ledger = &mut self.ledger;
+        self.players.retain(|player| {
+            if player.owner_id != Some(id) {
+                return true;
+            }
+            let mass = player.mass() + player.projectile.as_ref().map_or(0.0, |e| e.mass());
+            ledger.record(Some(id), |b| b.removed += mass);
+            false
+        });
+        self.update_grids();
+    }
+
+    /// Everything there is to gain or lose: players, their shots and food.
+    pub fn total_mass(&self) -> f32 {
+        self.players
+            .iter()
+            .map(|player| player.mass() + player.projectile.as_ref().map_or(0.0, |e| e.mass()))
+            .sum::<f32>()
+            + self.projectiles.iter().map(|e| e.mass()).sum::<f32>()
+            + self.food.iter().map(|f| f.mass()).sum::<f32>()
+    }
+
+    /// Rebuilds the lookup grids, needed after players, projectiles or food were added or removed.
+    fn update_grids(&mut self) {
+        let arena = &self.config.arena;
+        self.player_grid = SpatialHash::build(
+            arena,
+            Self::GRID_CELL_SIZE,
+            self.players.iter().map(|player| &**player),
+        );
+        self.player_indices = self
+            .players
+            .iter()
+            .enumerate()
+            .map(|(index, player)| (player.owner_id.unwrap(), index))
+            .collect();
+        self.food_grid =
+            SpatialHash::build(arena, Self::GRID_CELL_SIZE, self.food.iter().map(|f| &**f));
+        self.projectile_grid = SpatialHash::build(
+            arena,
+            Self::GRID_CELL_SIZE,
+            self.projectiles.iter().map(|e| &**e),
+        );
+        self.max_projectile_speed = self
+            .projectiles
+            .iter()
+            .map(|e| e.vel.len())
+            .fold(0.0, f32::max);
+    }
+
+    /// Closest food worth eating, that is not poisoned.
+    pub fn closest_food(&self, pos: Vec2<f32>) -> Option<&Food> {
+        self.food_grid
+            .nearest(pos, &self.config.arena, |index| {
+                self.food[index].kind != FoodKind::Poison
+            })
+            .map(|index| &self.food[index])
+    }
+
+    pub fn closest_player(
+        &self,
+        pos: Vec2<f32>,
+        filter: impl Fn(&Player) -> bool,
+    ) -> Option<&Player> {
+        self.player_grid
+            .nearest(pos, &self.config.arena, |index| {
+                filter(&self.players[index])
+            })
+            .map(|index| &self.players[index])
+    }
+
+    pub fn player(&self, id: usize) -> Option<&Player> {
+        self.player_indices
+            .get(&id)
+            .map(|&index| &self.players[index])
+    }
+
+    /// Players that may be within `radius` of `pos`, and a few more around.
+    pub fn players_near(&self, pos: Vec2<f32>, radius: f32) -> impl Iterator<Item = &Player> {
+        self.player_grid
+            .query(pos, radius)
+            .into_iter()
+            .map(move |index| &self.players[index])
+    }
+
+    /// Projectiles that may be within `radius` of `pos`, and a few more around.
+    pub fn projectiles_near(
+        &self,
+        pos: Vec2<f32>,
+        radius: f32,
+    ) -> impl Iterator<Item = &Projectile> {
+        self.projectile_grid
+            .query(pos, radius)
+            .into_iter()
+            .map(move |index| &self.projectiles[index])
+    }
+
+    pub fn max_projectile_speed(&self) -> f32 {
+        self.max_projectile_speed
+    }
+
+    /// Whether no obstacle stands between `a` and `b`.
+    pub fn line_of_sight(&self, a: Vec2<f32>, b: Vec2<f32>) -> bool {
+        self.map.line_of_sight(a, b, &self.config.arena)
+    }
+
+    pub fn player_alive(&self) -> bool {
+        self.players.iter().any(|player| player.team_id == 0)
+    }
+
+    pub fn enemy_count(&self) -> usize {
+        self.players.iter().filter(|p| p.team_id != 0).count()
+    }
+
+    /// Events emitted since the last call, oldest first.
+    pub fn take_events(&mut self) -> Vec<Event> {
+        mem::replace(&mut self.events, Vec::new())
+    }
+
+    /// Asks every player's controller for its next action,
+    /// in the same order as `players`.
+    pub fn act(&self) -> Vec<Action> {
+        self.players.iter().map(|player| player.act(self)).collect()
+    }
+
+    /// Skill of the bots spawned in the given wave, unless their group sets its own.
+    pub fn wave_difficulty(&self, wave: usize) -> Difficulty {
+        let scripted = self
+            .waves
+            .waves
+            .get(wave.max(1) - 1)
+            .and_then(|definition| definition.difficulty);
+        if let Some(difficulty) = scripted {
+            return difficulty;
+        }
+        match self.config.waves.harder_every {
+            0 => self.difficulty,
+            n => self.difficulty.harder((wave.max(1) - 1) / n),
+        }
+    }
+
+    /// Picks the kind of the next enemy in the given wave, by the archetypes' frequencies.
+    fn pick_archetype(&mut self, wave: usize) -> Archetype {
+        let archetypes = &self.config.archetypes;
+        let available: Vec<(Archetype, f32)> = Archetype::ALL
+            .iter()
+            .map(|&archetype| (archetype, archetypes.get(archetype)))
+            .filter(|&(archetype, stats)| {
+                archetype != Archetype::Boss && stats.first_wave <= wave && stats.frequency > 0.0
+            })
+            .map(|(archetype, stats)| (archetype, stats.frequency))
+            .collect();
+        let total: f32 = available.iter().map(|&(_, frequency)| frequency).sum();
+        if total <= 0.0 {
+            return Archetype::Grunt;
+        }
+        let mut choice = self.rng.gen_range(0.0..total);
+        for &(archetype, frequency) in &available {
+            if choice < frequency {
+                return archetype;
+            }
+            choice -= frequency;
+        }
+        available.last().unwrap().0
+    }
+
+    /// The scripted wave, or one made up by the endless rule once the script ran out.
+    fn wave_definition(&mut self, wave: usize) -> WaveDefinition {
+        if let Some(definition) = self.waves.waves.get(wave.max(1) - 1) {
+            return definition.clone();
+        }
+        let endless = self.waves.endless.clone();
+        let mut groups = Vec::new();
+        if self.waves.is_boss_wave(wave) {
+            groups.push(SpawnGroup {
+                archetype: Archetype::Boss,
+                count: 1,
+                pattern: endless.pattern,
+                delay: 0.0,
+            });
+        } else {
+            let count = endless.count + endless.count_growth * (wave - self.waves.waves.len() - 1);
+            let mut counts = HashMap::new();
+            for _ in 0..count {
+                *counts.entry(self.pick_archetype(wave)).or_insert(0) += 1;
+            }
+            for &archetype in &Archetype::ALL {
+                if let Some(&count) = counts.get(&archetype) {
+                    groups.push(SpawnGroup {
+                        archetype,
+                        count,
+                        pattern: endless.pattern,
+                        delay: 0.0,
+                    });
+                }
+            }
+        }
+        WaveDefinition {
+            groups,
+            time: None,
+            food_rate: endless.food_rate,
+        }
+    }
+
+    /// Random place away from the human team where a circle of `radius` touches no obstacle.
+    /// If there seems to be none, the place least inside an obstacle, then furthest from the humans.
+    fn spawn_pos(&mut self, radius: f32) -> Vec2<f32> {
+        let size = self.config.arena.size;
+        let mut best: Option<((f32, f32), Vec2<f32>)> = None;
+        for _ in 0..Self::SPAWN_ATTEMPTS {
+            let pos = vec2(
+                self.rng.gen_range(-size..=size),
+                self.rng.gen_range(-size..=size),
+            );
+            let overlap = self
+                .map
+                .contact(pos, radius, &self.config.arena)
+                .map(|(contact, _)| contact.depth);
+            let closest = self
+                .players
+                .iter()
+                .filter(|player| player.team_id == 0)
+                .map(|player| self.config.arena.delta_pos(player.pos, pos).len())
+                .fold(f32::INFINITY, f32::min);
+            if overlap.is_none() && closest > self.config.waves.spawn_distance {
+                return pos;
+            }
+            let score = (overlap.unwrap_or(0.0), -closest);
+            if best.map_or(true, |(best_score, _)| score < best_score) {
+                best = Some((score, pos));
+            }
+        }
+        best.unwrap().1
+    }
+
+    /// `pos` pushed out of the obstacles a circle of `radius` there would overlap,
+    /// or a random free place if that is not enough.
+    fn free_pos(&mut self, mut pos: Vec2<f32>, radius: f32) -> Vec2<f32> {
+        let arena = &self.config.arena;
+        for _ in 0..Self::PUSH_OUT_ATTEMPTS {
+            match self.map.contact(pos, radius, arena) {
+                Some((contact, _)) => pos = arena.normalize(pos + contact.normal * contact.depth),
+                None => return pos,
+            }
+        }
+        if self.map.contact(pos, radius, arena).is_none() {
+            return pos;
+        }
+        self.spawn_pos(radius)
+    }
+
+    /// Adds a bot of the given archetype, returning its index in `players`.
+    fn spawn_bot(&mut self, archetype: Archetype, pos: Vec2<f32>, difficulty: Difficulty) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        let controller =
+            BotController::with_behaviours(difficulty, self.rng.gen(), archetype.behaviours());
+        let size = self.config.player.initial_size * self.config.archetypes.get(archetype).size;
+        let pos = self.free_pos(pos, size);
+        let mut enemy = Player::new(id, pos, size, archetype.color(), controller, 1);
+        enemy.archetype = Some(archetype);
+        enemy.weapon = self.config.archetypes.get(archetype).weapon;
+        let mass = enemy.mass();
+        self.ledger.record(Some(id), |b| b.spawned += mass);
+        self.players.push(enemy);
+        self.players.len() - 1
+    }
+
+    fn spawn_group(&mut self, group: &SpawnGroup) {
+        // Patterns are laid out around the first player of the human team
+        let target = self
+            .players
+            .iter()
+            .find(|player| player.team_id == 0)
+            .map_or(vec2(0.0, 0.0), |player| player.pos);
+        let center = match group.pattern {
+            SpawnPattern::Cluster => self.spawn_pos(Self::CLUSTER_RADIUS),
+            _ => target,
+        };
+        let difficulty = group
+            .difficulty
+            .unwrap_or_else(|| self.wave_difficulty(self.next_wave));
+        let start_angle = self.rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+        for i in 0..group.count {
+            let pos = match group.pattern {
+                SpawnPattern::Random => self.spawn_pos(self.config.player.initial_size),
+                SpawnPattern::Ring => {
+                    let angle =
+                        start_angle + 2.0 * std::f32::consts::PI * i as f32 / group.count as f32;
+                    center + vec2(angle.cos(), angle.sin()) * self.config.waves.spawn_distance
+                }
+                SpawnPattern::Edge => {
+                    let distance = self.config.waves.edge_distance;
+                    let along = self.rng.gen_range(-distance..=distance);
+                    center
+                        + match self.rng.gen_range(0..4) {
+                            0 => vec2(-distance, along),
+                            1 => vec2(distance, along),
+                            2 => vec2(along, -distance),
+                            _ => vec2(along, distance),
+                        }
+                }
+                SpawnPattern::Cluster => {
+                    center + random_circle_point(&mut self.rng) * Self::CLUSTER_RADIUS
+                }
+            };
+            let pos = self
+                .config
+                .arena
+                .confine(pos, self.config.player.initial_size);
+            match group.archetype {
+                Archetype::Boss => self.spawn_boss(pos, difficulty),
+                archetype => {
+                    self.spawn_bot(archetype, pos, difficulty);
+                }
+            }
+        }
+    }
+
+    fn spawn_boss(&mut self, pos: Vec2<f32>, difficulty: Difficulty) {
+        let index = self.spawn_bot(Archetype::Boss, pos, difficulty);
+        self.players[index].boss = Some(Boss::new(&self.config.boss));
+        self.events.push(Event::BossSpawn { pos });
+    }
+
+    fn spawn_minions(&mut self, boss_pos: Vec2<f32>, boss_size: f32) {
+        for _ in 0..self.config.boss.minion_count {
+            let angle = self.rng.gen_range(0.0..2.0 * std::f32::consts::PI);
+            let offset = vec2(angle.cos(), angle.sin()) * (boss_size + 2.0);
+            let pos = self
+                .config
+                .arena
+                .confine(boss_pos + offset, self.config.player.initial_size);
+            self.spawn_bot(
+                self.config.boss.minion,
+                pos,
+                self.wave_difficulty(self.next_wave),
+            );
+        }
+    }
+
+    /// Advances the simulation, `actions` being parallel to `players`.
+    pub fn step(&mut self, delta_time: f32, actions: &[Action]) {
+        assert_eq!(actions.len(), self.players.len());
+        self.ledger.start_tick();
+        let config = &self.config;
+        let player_alive = self.player_alive();
+
+        for (player, &action) in self.players.iter_mut().zip(actions) {
+            player.action = action;
+            if player.team_id == 0 {
+                self.start = self.start.max(player.pos.len());
+            }
+        }
+
+        for player in &mut self.players {
+            let modifiers = player.modifiers(config);
+            if self.start > config.start_distance {
+                let prev_mass = player.mass();
+                player.size = (player.size
+                    - config.player.death_speed * modifiers.decay * delta_time)
+                    .max(0.0);
+                let lost = prev_mass - player.mass();
+                self.ledger.record(player.owner_id, |b| b.decay += lost);
+            }
+            if modifiers.drain > 0.0 {
+                let prev_mass = player.mass();
+                player.add_mass(-modifiers.drain * delta_time);
+                let lost = prev_mass - player.mass();
+                self.ledger.record(player.owner_id, |b| b.decay += lost);
+            }
+            let prev_mass = player.mass();
+            let prev_projectile_mass = player.projectile.as_ref().map_or(0.0, |e| e.mass());
+            let released = player.update(delta_time, config, &mut self.cosmetic_rng);
+            let cost = prev_mass - player.mass();
+            let gain = player
+                .projectile
+                .as_ref()
+                .or(released.as_ref())
+                .map_or(0.0, |e| e.mass())
+                - prev_projectile_mass;
+            self.ledger.record(player.owner_id, |b| {
+                b.charge_cost += cost;
+                b.charge_gain += gain;
+            });
+            if let Some(e) = released {
+                self.events.push(Event::Shoot { pos: e.pos });
+                self.projectiles
+                    .extend(e.weapon.release(e, &config.weapons));
+            }
+            if player.size <= 0.0 {
+                let food = Food::new(
+                    player.pos,
+                    // As much as the player started with
+                    config.player.initial_size * player.stats(config).size / config.food.k.sqrt(),
+                    FoodKind::Plain,
+                    &mut self.cosmetic_rng,
+                );
+                let dropped = food.mass();
+                let removed = player.projectile.as_ref().map_or(0.0, |e| e.mass());
+                self.ledger.record(player.owner_id, |b| {
+                    b.dropped += dropped;
+                    b.removed += removed;
+                });
+                self.food.push(food);
+                self.events.push(match player.boss {
+                    Some(_) => Event::BossDeath { pos: player.pos },
+                    None => Event::Death { pos: player.pos },
+                });
+                if player.team_id != 0 && player_alive {
+                    self.kills += 1;
+                }
+            }
+        }
+        self.players.retain(|e| e.size > 0.0);
+        let mut minion_spawns = Vec::new();
+        for player in &mut self.players {
+            let attack = match player.boss {
+                Some(ref mut boss) => boss.update(delta_time, &config.boss),
+                None => continue,
+            };
+            match attack {
+                Some(BossAttack::Burst) => {
+                    let burst_mass =
+                        config.boss.burst_count as f32 * config.boss.burst_size.powf(2.0);
+                    // Never shoot itself to death
+                    if player.mass() > burst_mass * 2.0 {
+                        player.add_mass(-burst_mass);
+                        self.ledger.record(player.owner_id, |b| {
+                            b.charge_cost += burst_mass;
+                            b.charge_gain += burst_mass;
+                        });
+                        for i in 0..config.boss.burst_count {
+                            let angle = 2.0 * std::f32::consts::PI * i as f32
+                                / config.boss.burst_count as f32;
+                            let dir = vec2(angle.cos(), angle.sin());
+                            let mut e =
+                                Projectile::new(player.owner_id, mix(player.color, Color::WHITE));
+                            e.pos = config.arena.confine(
+                                player.pos + dir * (player.size + config.boss.burst_size),
+                                config.boss.burst_size,
+                            );
+                            e.prev_pos = e.pos;
+                            e.vel = dir * config.player.projectile_speed;
+                            e.size = config.boss.burst_size;
+                            self.projectiles.push(e);
+                        }
+                        self.events.push(Event::Shoot { pos: player.pos });
+                    }
+                }
+                Some(BossAttack::Minions) => minion_spawns.push((player.pos, player.size)),
+                Some(BossAttack::Vacuum) | None => {}
+            }
+            if player.boss.as_ref().map_or(false, |boss| boss.vacuuming()) {
+                for f in &mut self.food {
+                    let delta = config.arena.delta_pos(f.pos, player.pos);
+                    let distance = delta.len();
+                    if distance < config.boss.vacuum_radius && distance > 1e-5 {
+                        let step = (config.boss.vacuum_speed * delta_time).min(distance);
+                        f.pos = config.arena.normalize(f.pos + delta / distance * step);
+                        // Pulled along obstacles, not into them
+                        if let Some((contact, _)) = self.map.contact(f.pos, f.size, &config.arena) {
+                            f.pos = config
+                                .arena
+                                .normalize(f.pos + contact.normal * contact.depth);
+                        }
+                    }
+                }
+            }
+        }
+        // Players moved and died since the grids were last built
+        let player_grid = SpatialHash::build(
+            &config.arena,
+            Self::GRID_CELL_SIZE,
+            self.players.iter().map(|player| &**player),
+        );
+        let teams: HashMap<usize, usize> = self
+            .players
+            .iter()
+            .map(|player| (player.owner_id.unwrap(), player.team_id))
+            .collect();
+        for e in &mut self.projectiles {
+            if e.weapon == Weapon::Homing && e.size > 0.0 {
+                let team_id = e.owner_id.and_then(|id| teams.get(&id).copied());
+                Self::steer_homing(e, team_id, &self.players, &player_grid, config, delta_time);
+            }
+            let death_speed = match e.weapon {
+                Weapon::Mine => config.projectile.death_speed * config.weapons.mine_decay,
+                _ => config.projectile.death_speed,
+            };
+            let prev_mass = e.mass();
+            e.size = (e.size - death_speed * delta_time).max(0.0);
+            let lost = prev_mass - e.mass();
+            self.ledger
+                .record(e.owner_id, |b| b.projectile_decay += lost);
+            e.update(delta_time, &config.arena, &mut self.cosmetic_rng);
+            if e.size > 0.0 {
+                if let Some((contact, response)) = self.map.contact(e.pos, e.size, &config.arena) {
+                    match response {
+                        ProjectileResponse::Bounce => {
+                            e.pos = config
+                                .arena
+                                .normalize(e.pos + contact.normal * contact.depth);
+                            let along = Vec2::dot(e.vel, contact.normal);
+                            if along < 0.0 {
+                                e.vel -= contact.normal * along * 2.0;
+                            }
+                        }
+                        ProjectileResponse::Absorb => {
+                            let absorbed = e.mass();
+                            e.size = 0.0;
+                            self.ledger.record(e.owner_id, |b| b.absorbed += absorbed);
+                        }
+                    }
+                }
+            }
+            if e.just_died() && e.actually_hit {
+                self.events.push(Event::Hit { pos: e.pos });
+            }
+        }
+        self.projectiles.retain(|e| e.alive());
+        let player_grid = SpatialHash::build(
+            &config.arena,
+            Self::GRID_CELL_SIZE,
+            self.players.iter().map(|player| &**player),
+        );
+        for i in 0..self.players.len() {
+            let (head, tail) = self.players.split_at_mut(i);
+            let cur = &mut tail[0];
+            for j in player_grid.query(cur.pos, cur.size) {
+                if j < i {
+                    Entity::collide(&mut head[j], cur, &config.arena);
+                }
+            }
+        }
+        for player in &mut self.players {
+            if let Some((contact, _)) = self.map.contact(player.pos, player.size, &config.arena) {
+                player.pos = config
+                    .arena
+                    .normalize(player.pos + contact.normal * contact.depth);
+                let along = Vec2::dot(player.vel, contact.normal);
+                if along < 0.0 {
+                    player.vel -= contact.normal * along;
+                }
+            }
+        }
+        let player_grid = SpatialHash::build(
+            &config.arena,
+            Self::GRID_CELL_SIZE,
+            self.players.iter().map(|player| &**player),
+        );
+        for i in 0..self.players.len() {
+            let healer = &self.players[i];
+            let feed_speed = healer.stats(config).feed_speed;
+            if feed_speed <= 0.0 {
+                continue;
+            }
+            let ally = player_grid
+                .query(healer.pos, healer.size + Self::FEED_REACH)
+                .into_iter()
+                .filter(|&j| {
+                    let ally = &self.players[j];
+                    j != i
+                        && ally.team_id == healer.team_id
+                        && ally.size < healer.size
+                        && config.arena.delta_pos(healer.pos, ally.pos).len()
+                            < healer.size + ally.size + Self::FEED_REACH
+                })
+                .min_by(|&a, &b| {
+                    self.players[a]
+                        .size
+                        .partial_cmp(&self.players[b].size)
+                        .unwrap()
+                });
+            if let Some(j) = ally {
+                // Never give so much that the ally ends up bigger
+                let mass =
+                    (feed_speed * delta_time).min((healer.mass() - self.players[j].mass()) / 2.0);
+                let healer_id = healer.owner_id;
+                self.players[i].add_mass(-mass);
+                self.players[j].add_mass(mass);
+                self.ledger.record(healer_id, |b| b.fed += mass);
+                self.ledger
+                    .record(self.players[j].owner_id, |b| b.fed_gain += mass);
+            }
+        }
+        for e in &mut self.projectiles {
+            for j in player_grid.query(e.pos, e.swept_radius()) {
+                let player = &mut self.players[j];
+                if e.owner_id != player.owner_id {
+                    let prev_masses = (e.mass(), player.mass());
+                    if e.size > 0.0
+                        && player.has_status(StatusKind::Shield)
+                        && Entity::swept_distance(e, player, &config.arena) < e.size + player.size
+                    {
+                        // Absorbed whole, and the shield is gone
+                        e.size = 0.0;
+                        e.actually_hit = true;
+                        player.remove_status(StatusKind::Shield);
+                        Self::record_hit(&mut self.ledger, e, player, prev_masses);
+                        continue;
+                    }
+                    let strength = config.projectile.strength * player.modifiers(config).damage;
+                    if e.weapon == Weapon::Lance {
+                        // Goes through, paying a share of its mass once per player
+                        let id = player.owner_id.unwrap();
+                        if e.size > 0.0
+                            && !e.pierced.contains(&id)
+                            && Entity::swept_distance(e, player, &config.arena)
+                                < e.size + player.size
+                        {
+                            let spent = e.mass() * config.weapons.lance_pierce_cost;
+                            e.add_mass(-spent);
+                            player.add_mass(-spent * strength);
+                            e.pierced.push(id);
+                            e.actually_hit = true;
+                            Self::record_hit(&mut self.ledger, e, player, prev_masses);
+                        }
+                        continue;
+                    }
+                    if e.hit(player, strength, &config.arena) {
+                        e.actually_hit = true;
+                        Self::record_hit(&mut self.ledger, e, player, prev_masses);
+                    }
+                }
+            }
+        }
+        let projectile_grid = SpatialHash::build(
+            &config.arena,
+            Self::GRID_CELL_SIZE,
+            self.projectiles.iter().map(|e| &**e),
+        );
+        for i in 0..self.projectiles.len() {
+            let (head, tail) = self.projectiles.split_at_mut(i);
+            let cur = &mut tail[0];
+            for j in projectile_grid.query(cur.pos, cur.swept_radius()) {
+                if j < i {
+                    let prev = &mut head[j];
+                    // Pellets of one shot start out on top of each other
+                    if cur.weapon == Weapon::Shotgun
+                        && prev.weapon == Weapon::Shotgun
+                        && cur.owner_id == prev.owner_id
+                    {
+                        continue;
+                    }
+                    let prev_masses = (cur.mass(), prev.mass());
+                    if cur.hit(prev, 1.0, &config.arena) {
+                        Self::record_hit(&mut self.ledger, cur, prev, prev_masses);
+                    }
+                }
+            }
+        }
+        self.next_food -= delta_time * self.food_rate;
+        while self.next_food < 0.0 {
+            let food_config = &config.food;
+            let size = config.arena.size;
+            self.next_food += self
+                .rng
+                .gen_range(food_config.spawn_interval.start..=food_config.spawn_interval.end);
+            if self.food.len() < food_config.max_count {
+                let roll = self.rng.gen_range(0.0..1.0);
+                let kind = if roll < food_config.poison_chance {
+                    FoodKind::Poison
+                } else if roll < food_config.poison_chance + food_config.power_up_chance {
+                    FoodKind::POWER_UPS[self.rng.gen_range(0..FoodKind::POWER_UPS.len())]
+                } else {
+                    FoodKind::Plain
+                };
+                let food = Food::new(
+                    vec2(
+                        self.rng.gen_range(-size..=size),
+                        self.rng.gen_range(-size..=size),
+                    ),
+                    food_config.size.start
+                        + self.rng.gen_range(0.0f32..=1.0f32).powf(4.0)
+                            * (food_config.size.end - food_config.size.start),
+                    kind,
+                    &mut self.cosmetic_rng,
+                );
+                // Food never grows inside an obstacle, that spawn is skipped
+                if self
+                    .map
+                    .contact(food.pos, food.size, &config.arena)
+                    .is_none()
+                {
+                    let mass = food.mass();
+                    self.ledger.record(None, |b| b.food_spawned += mass);
+                    self.food.push(food);
+                }
+            }
+        }
+        for f in &mut self.food {
+            f.update(delta_time);
+        }
+        for f in &mut self.food {
+            for j in player_grid.query(f.pos, f.size) {
+                let player = &mut self.players[j];
+                let prev_masses = (player.mass(), f.mass());
+                let k = match f.kind {
+                    FoodKind::Poison => 0.0,
+                    _ => config.food.k * player.stats(config).food_gain,
+                };
+                player.consume(f, k, &config.arena);
+                let gain = player.mass() - prev_masses.0;
+                let eaten = prev_masses.1 - f.mass();
+                self.ledger.record(player.owner_id, |b| {
+                    b.eaten += eaten;
+                    b.eating_gain += gain;
+                });
+                // The effect goes to whoever finishes the food
+                if f.size <= 0.0 && prev_masses.1 > 0.0 {
+                    if let Some(effect) = f.kind.effect() {
+                        player.apply_status(effect, &config.status_effects);
+                    }
+                }
+            }
+            if f.size <= 0.0 {
+                self.events.push(Event::Heal { pos: f.pos });
+            }
+        }
+        self.food.retain(|e| e.size > 0.0);
+
+        for (pos, size) in minion_spawns {
+            self.spawn_minions(pos, size);
+        }
+        if self.enemy_count() == 0 && self.pending_spawns.is_empty() {
+            self.next_wave_timer = self.next_wave_timer.min(self.config.waves.pause);
+        }
+        if self.start > self.config.start_distance {
+            self.next_wave_timer -= delta_time;
+            if self.next_wave_timer < 0.0 {
+                let wave = self.wave_definition(self.next_wave);
+                self.next_wave_timer = wave.time.unwrap_or(self.config.waves.time_between);
+                self.food_rate = wave.food_rate;
+                self.pending_spawns.extend(wave.groups);
+                if player_alive {
+                    self.waves_finished = self.next_wave.max(1) - 1;
+                }
+                self.next_wave += 1;
+            }
+            for group in &mut self.pending_spawns {
+                group.delay -= delta_time;
+            }
+            let (due, pending): (Vec<_>, Vec<_>) =
+                mem::replace(&mut self.pending_spawns, Vec::new())
+                    .into_iter()
+                    .partition(|group| group.delay <= 0.0);
+            self.pending_spawns = pending;
+            for group in &due {
+                self.spawn_group(group);
+            }
+            if player_alive {
+                self.time_played += delta_time;
+            }
+        }
+        self.update_grids();
+        self.tick += 1;
+    }
+
+    /// Turns a homing orb of `team_id` towards the closest enemy in range.
+    fn steer_homing(
+        e: &mut Projectile,
+        team_id: Option<usize>,
+        players: &[Player],
+        player_grid: &SpatialHash,
+        config: &Config,
+        delta_time: f32,
+    ) {
+        let target = player_grid
+            .query(e.pos, config.weapons.homing_range)
+            .into_iter()
+            .map(|index| &players[index])
+            .filter(|player| player.owner_id != e.owner_id && Some(player.team_id) != team_id)
+            .map(|player| config.arena.delta_pos(e.pos, player.pos))
+            .filter(|delta| delta.len() < config.weapons.homing_range)
+            .min_by(|a, b| a.len().partial_cmp(&b.len()).unwrap());
+        let delta = match target {
+            Some(delta) => delta,
+            None => return,
+        };
+        let mut turn = delta.arg() - e.vel.arg();
+        while turn > std::f32::consts::PI {
+            turn -= 2.0 * std::f32::consts::PI;
+        }
+        while turn < -std::f32::consts::PI {
+            turn += 2.0 * std::f32::consts::PI;
+        }
+        let max_turn = config.weapons.homing_turn_speed * delta_time;
+        e.vel = e.vel.rotate(clamp(turn, -max_turn..=max_turn));
+    }
+
+    fn record_hit(
+        ledger: &mut MassLedger,
+        projectile: &Entity,
+        target: &Entity,
+        (prev_mass, prev_target_mass): (f32, f32),
+    ) {
+        let spent = prev_mass - projectile.mass();
+        let damage = prev_target_mass - target.mass();
+        ledger.record(projectile.owner_id, |b| b.hit_spent += spent);
+        ledger.record(target.owner_id, |b| b.hit_damage += damage);
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            tick: self.tick,
+            player_id: None,
+            last_input: 0,
+            events: Vec::new(),
+            players: self
+                .players
+                .iter()
+                .map(|player| PlayerState {
+                    entity: Entity::clone(player),
+                    team_id: player.team_id,
+                    archetype: player.archetype,
+                    action: player.action,
+                    projectile: player.projectile.as_ref().map(|e| Entity::clone(e)),
+                    weapon: player.weapon,
+                    status_effects: player.status_effects.clone(),
+                })
+                .collect(),
+            projectiles: self
+                .projectiles
+                .iter()
+                .filter(|e| e.size > 0.0)
+                .map(|e| (Entity::clone(e), e.weapon))
+                .collect(),
+            food: self
+                .food
+                .iter()
+                .map(|f| (Entity::clone(f), f.kind))
+                .collect(),
+            next_wave_timer: self.next_wave_timer,
+            next_wave: self.next_wave,
+            time_played: self.time_played,
+            kills: self.kills,
+            waves_finished: self.waves_finished,
+            start: self.start,
+        }
+    }
+
+    /// Replaces the state with the one received from the server,
+    /// keeping local objects (and so their particles) alive where possible.
+    /// Previous positions are kept so that rendering interpolates between snapshots.
+    pub fn apply_snapshot(&mut self, snapshot: &Snapshot) {
+        let mut players = mem::replace(&mut self.players, Vec::new());
+        for state in &snapshot.players {
+            let id = state.entity.owner_id.unwrap();
+            let mut player = match players.iter().position(|p| p.owner_id == Some(id)) {
+                Some(index) => players.swap_remove(index),
+                None => Player::new(
+                    id,
+                    state.entity.pos,
+                    state.entity.size,
+                    state.entity.color,
+                    RemoteController::new(&Rc::new(Cell::new(default()))),
+                    state.team_id,
+                ),
+            };
+            let prev_pos = player.pos;
+            *player = state.entity.clone();
+            player.prev_pos = player.pos - self.config.arena.delta_pos(prev_pos, player.pos);
+            player.archetype = state.archetype;
+            player.action = state.action;
+            player.weapon = state.weapon;
+            player.status_effects = state.status_effects.clone();
+            match state.projectile {
+                Some(ref e) => {
+                    let projectile = player
+                        .projectile
+                        .get_or_insert_with(|| Projectile::new(e.owner_id, e.color));
+                    **projectile = e.clone();
+                }
+                None => player.projectile = None,
+            }
+            self.players.push(player);
+        }
+
+        let arena = &self.config.arena;
+        let mut projectiles = mem::replace(&mut self.projectiles, Vec::new());
+        for &(ref e, weapon) in &snapshot.projectiles {
+            let closest = projectiles
+                .iter()
+                .enumerate()
+                .filter(|&(_, p)| p.owner_id == e.owner_id && p.size > 0.0)
+                .min_by(|&(_, a), &(_, b)| {
+                    arena
+                        .delta_pos(a.pos, e.pos)
+                        .len()
+                        .partial_cmp(&arena.delta_pos(b.pos, e.pos).len())
+                        .unwrap()
+                })
+                .map(|(index, _)| index);
+            let (mut projectile, prev_pos) = match closest {
+                Some(index) => {
+                    let projectile = projectiles.swap_remove(index);
+                    let prev_pos = projectile.pos;
+                    (projectile, prev_pos)
+                }
+                None => (Projectile::new(e.owner_id, e.color), e.pos),
+            };
+            *projectile = e.clone();
+            projectile.prev_pos = projectile.pos - arena.delta_pos(prev_pos, projectile.pos);
+            projectile.weapon = weapon;
+            self.projectiles.push(projectile);
+        }
+        for mut projectile in projectiles {
+            // Let the sparks fade out
+            projectile.size = 0.0;
+            if projectile.alive() {
+                self.projectiles.push(projectile);
+            }
+        }
+
+        let mut food = mem::replace(&mut self.food, Vec::new());
+        for &(ref e, kind) in &snapshot.food {
+            self.food
+                .push(match food.iter().position(|f| f.pos == e.pos) {
+                    Some(index) => {
+                        let mut f = food.swap_remove(index);
+                        f.size = e.size;
+                        f
+                    }
+                    None => Food::new(e.pos, e.size, kind, &mut self.cosmetic_rng),
+                });
+        }
+
+        self.tick = snapshot.tick;
+        self.next_wave_timer = snapshot.next_wave_timer;
+        self.next_wave = snapshot.next_wave;
+        self.time_played = snapshot.time_played;
+        self.kills = snapshot.kills;
+        self.waves_finished = snapshot.waves_finished;
+        self.start = snapshot.start;
+        self.update_grids();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every change of mass, by any weapon, effect, wave or boss, goes through the ledger.
+    #[test]
+    fn ledger_accounts_for_all_mass() {
+        const TICKS: usize = 5000;
+        let config = Config::parse(include_str!("../static/config.json")).unwrap();
+        let delta_time = config.tick_duration();
+        let mut world = World::empty(42, config);
+        world.difficulty = Difficulty::Nightmare;
+        world.waves = WaveScript::parse(include_str!("../static/waves.json")).unwrap();
+        world.map = Map::parse(include_str!("../static/map.json")).unwrap();
+        for seed in 0..2 {
+            world.add_player(BotController::new(Difficulty::Hard, seed));
+        }
+        for tick in 0..TICKS {
+            // Someone has to be around for the waves to keep coming
+            if !world.player_alive() {
+                world.add_player(BotController::new(Difficulty::Hard, tick as u64));
+            }
+            let actions = world.act();
+            world.step(delta_time, &actions);
+            let mass = world.total_mass();
+            let epsilon = 1e-3 * world.ledger.total.created().max(1.0);
+            let drift = world.ledger.drift(mass);
+            assert!(
+                drift.abs() <= epsilon,
+                "mass drifted by {} at tick {}",
+                drift,
+                tick,
+            );
+        }
+    }
+}